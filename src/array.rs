@@ -0,0 +1,157 @@
+use std::cmp::{min, max};
+
+use crate::coord::Coord;
+use crate::grid::Grid;
+use crate::rule::Rule;
+use crate::state::State;
+use crate::stats::Stats;
+
+// Fixed size plane, cells beyond its edges are always dead. As in Golly, the plane is
+// centered at (0, 0) with its upper left corner at (-width / 2, -height / 2).
+pub struct ArrayGrid {
+    width: i64,
+    height: i64,
+    origin: Coord<i64>,
+    cells: Vec<bool>,
+    rule: Rule,
+    generation: u64,
+    population: u64,
+}
+
+impl ArrayGrid {
+    pub fn new(width: i64, height: i64) -> ArrayGrid {
+        assert!(width > 0 && height > 0, "Grid dimensions must be positive");
+        ArrayGrid {
+            width,
+            height,
+            origin: Coord(-width / 2, -height / 2),
+            cells: vec![false; (width * height) as usize],
+            rule: Rule::default(),
+            generation: 0,
+            population: 0,
+        }
+    }
+
+    pub fn size(&self) -> (i64, i64) {
+        (self.width, self.height)
+    }
+
+    fn index(&self, coord: Coord<i64>) -> Option<usize> {
+        let (x, y) = (coord.0 - self.origin.0, coord.1 - self.origin.1);
+        if x >= 0 && x < self.width && y >= 0 && y < self.height {
+            Some((y * self.width + x) as usize)
+        } else {
+            None
+        }
+    }
+
+    fn coord(&self, index: usize) -> Coord<i64> {
+        let i = index as i64;
+        Coord(self.origin.0 + i % self.width, self.origin.1 + i / self.width)
+    }
+
+    fn ncount(&self, x: i64, y: i64) -> u8 {
+        let mut n = 0;
+        for ny in max(y - 1, 0)..min(y + 2, self.height) {
+            for nx in max(x - 1, 0)..min(x + 2, self.width) {
+                if !(nx == x && ny == y) && self.cells[(ny * self.width + nx) as usize] {
+                    n += 1;
+                }
+            }
+        }
+        n
+    }
+}
+
+impl Grid<i64> for ArrayGrid {
+    fn get(&self, coord: Coord<i64>) -> Option<State> {
+        match self.index(coord) {
+            Some(i) if self.cells[i] => Some(State::ALIVE),
+            _ => None,
+        }
+    }
+
+    fn set(&mut self, coord: Coord<i64>, _s: State) {
+        if let Some(i) = self.index(coord) {
+            if !self.cells[i] {
+                self.cells[i] = true;
+                self.population += 1;
+            }
+        }
+    }
+
+    fn kill(&mut self, coord: Coord<i64>) {
+        if let Some(i) = self.index(coord) {
+            if self.cells[i] {
+                self.cells[i] = false;
+                self.population -= 1;
+            }
+        }
+    }
+
+    fn iter(&self) -> Box<dyn Iterator<Item = Coord<i64>> + '_> {
+        Box::new(self.cells.iter().enumerate().filter(|(_, c)| **c).map(|(i, _)| self.coord(i)))
+    }
+
+    fn clear(&mut self) {
+        self.cells.fill(false);
+        self.population = 0;
+    }
+
+    fn dims(&self) -> (Coord<i64>, Coord<i64>) {
+        let mut cells = self.iter();
+        let first = cells.next().expect("Empty grid has no dimensions");
+        cells.fold((first, first), |(ul, lr), c| {
+            (Coord(min(ul.0, c.0), min(ul.1, c.1)), Coord(max(lr.0, c.0), max(lr.1, c.1)))
+        })
+    }
+
+    fn rule(&self) -> Rule {
+        self.rule.clone()
+    }
+
+    fn set_rule(&mut self, rule: Rule) -> Result<(), String> {
+        if !rule.is_plain_life_like() {
+            return Err("Array grid supports only two-state totalistic Moore rules on the infinite plane, use the sparse map for others".to_string());
+        }
+        self.rule = rule;
+        Ok(())
+    }
+
+    fn generation(&self) -> u64 {
+        self.generation
+    }
+
+    fn set_generation(&mut self, generation: u64) {
+        self.generation = generation;
+    }
+
+    fn population(&self) -> u64 {
+        self.population
+    }
+
+    fn step(&mut self) -> Stats {
+        let mut next = vec![false; self.cells.len()];
+        let mut stats = Stats::default();
+        for y in 0..self.height {
+            for x in 0..self.width {
+                let i = (y * self.width + x) as usize;
+                let alive = self.cells[i];
+                next[i] = self.rule.next(alive, self.ncount(x, y));
+                match (alive, next[i]) {
+                    (false, true) => stats.births += 1,
+                    (true, false) => stats.deaths += 1,
+                    _ => (),
+                }
+                if next[i] {
+                    stats.population += 1;
+                }
+            }
+        }
+
+        self.cells = next;
+        self.population = stats.population;
+        self.generation += 1;
+        stats
+    }
+}
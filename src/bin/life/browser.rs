@@ -0,0 +1,137 @@
+use std::path::{Path, PathBuf};
+use pancurses::*;
+
+use crate::prompt;
+
+const NEW_FILE: &str = "<new file>";
+
+// Lets the user walk the directory tree in a window covering the middle of the screen.
+// Returns the chosen file, or None if cancelled with Escape. When saving, the list also
+// offers a new file whose name is asked for, and overwriting an existing file is confirmed.
+pub fn browse(win: &Window, save: bool) -> Option<PathBuf> {
+    let fowin = win.subwin(
+        win.get_max_y() / 2,
+        win.get_max_x() / 2,
+        win.get_max_y() / 4,
+        win.get_max_x() / 4,
+    ).expect("Cannot create subwindow");
+
+    fowin.keypad(true);
+
+    let line_width: usize = (win.get_max_x() / 2 - 4).try_into().unwrap();
+    let num_lines: usize = (win.get_max_y() / 2 - 2).try_into().unwrap();
+
+    let res = 'dir: loop {
+        let mut cursor = 1;
+        let mut first_line: usize = 0;
+        let cwd = std::env::current_dir().unwrap();
+        fowin.erase();
+        // An unreadable directory is shown empty, so it is still possible to go back up
+        let mut entries = std::fs::read_dir(&cwd).map(|dir| dir.filter_map(|res| res.ok()).map(|e| {
+            let path = e.path();
+            (path.file_name().unwrap().to_string_lossy().into_owned(), path)
+        }).collect::<Vec<_>>()).unwrap_or_default();
+        entries.sort_by(|a, b| {
+            if a.1.is_dir() && !b.1.is_dir() {
+                return std::cmp::Ordering::Less;
+            }
+            if !a.1.is_dir() && b.1.is_dir() {
+                return std::cmp::Ordering::Greater;
+            }
+            a.cmp(b)
+        });
+        let mut up = cwd.clone();
+        up.push(Path::new(".."));
+        entries.insert(0, (String::from(".."), up));
+        if save {
+            entries.insert(1, (String::from(NEW_FILE), cwd.clone()));
+        }
+        loop {
+            fowin.attroff(A_REVERSE);
+            fowin.attron(A_ALTCHARSET);
+            fowin.border(ACS_VLINE(), ACS_VLINE(), ACS_HLINE(), ACS_HLINE(), ACS_ULCORNER(), ACS_URCORNER(), ACS_LLCORNER(), ACS_LRCORNER());
+            fowin.attroff(A_ALTCHARSET);
+            fowin.mv(0, 4);
+            fowin.addstr(format!(" {}{} ", if save { "Save to " } else { "" }, cwd.to_string_lossy()));
+            let mut line = 1;
+            for e in &entries[first_line..(std::cmp::min(first_line + num_lines, entries.len()))] {
+                let mut fname = e.0.clone();
+                if e.1.is_dir() && fname != NEW_FILE {
+                    fname += "/";
+                }
+                if line == cursor {
+                    fowin.attron(A_REVERSE);
+                } else {
+                    fowin.attroff(A_REVERSE);
+                }
+                fowin.mvaddstr(line.try_into().unwrap(), 2, format!("{:<1$}", fname, line_width));
+                line += 1;
+            }
+
+            fowin.refresh();
+
+            if let Some(ch) = fowin.getch() {
+                match ch {
+                    Input::KeyDown => {
+                        if cursor == line - 1 && first_line + num_lines < entries.len() {
+                            first_line += 1;
+                            fowin.erase();
+                        }
+                        if cursor < line - 1 {
+                            cursor += 1;
+                        }
+                    },
+                    Input::KeyUp => {
+                        if cursor == 1 && first_line > 0 {
+                            first_line -= 1;
+                            fowin.erase();
+                        }
+                        if cursor > 1 {
+                            cursor -= 1;
+                        }
+                    },
+                    Input::KeyNPage => {
+                        let new_line = std::cmp::min(first_line + cursor - 1 + num_lines, entries.len());
+                        first_line = std::cmp::min(first_line + num_lines, entries.len().saturating_sub(num_lines));
+                        cursor = std::cmp::min(new_line - first_line + 1, std::cmp::min(num_lines, entries.len()));
+                        fowin.erase();
+                    },
+                    Input::KeyPPage => {
+                        let new_line = (first_line + cursor - 1).saturating_sub(num_lines);
+                        first_line = first_line.saturating_sub(num_lines);
+                        cursor = new_line - first_line + 1;
+                        fowin.erase();
+                    },
+                    Input::Character('\x1b') => break 'dir None,
+                    Input::Character('\x0a') => {
+                        let e = &entries[first_line + cursor - 1];
+                        if save && first_line + cursor - 1 == 1 {
+                            match prompt(win, "File name: ") {
+                                Some(name) if !name.trim().is_empty() => {
+                                    let mut path = cwd.join(name.trim());
+                                    if path.extension().is_none() {
+                                        path.set_extension("rle");
+                                    }
+                                    break 'dir Some(path);
+                                },
+                                _ => continue 'dir,
+                            }
+                        } else if e.1.is_dir() {
+                            let mut newcwd = cwd.clone();
+                            newcwd.push(&e.1);
+                            let _res = std::env::set_current_dir(newcwd);
+                            continue 'dir;
+                        } else if !save || prompt(win, &format!("Overwrite {}? (y/n) ", e.0)).is_some_and(|a| a.trim() == "y") {
+                            break 'dir Some(e.1.clone());
+                        } else {
+                            continue 'dir;
+                        }
+                    }
+                    _ => ()
+                }
+            }
+        }
+    };
+    fowin.delwin();
+    res
+}
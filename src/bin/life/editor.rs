@@ -0,0 +1,119 @@
+use pancurses::Input;
+
+use life::coord::Coord;
+use life::grid::Grid;
+use life::region::{PasteMode, Region};
+use life::shapes;
+use life::state::State;
+
+// Edit mode state: a cursor moving over the board, an optional mark used as the other end
+// of lines, rectangles and selections, the pen which either draws cells in the chosen state
+// or erases them, and the clipboard
+pub struct Editor {
+    cursor: Coord<i64>,
+    mark: Option<Coord<i64>>,
+    erase: bool,
+    state: State,
+    clipboard: Option<Region>,
+    paste_mode: PasteMode,
+}
+
+impl Editor {
+    pub fn new(cursor: Coord<i64>) -> Editor {
+        Editor {
+            cursor,
+            mark: None,
+            erase: false,
+            state: State::ALIVE,
+            clipboard: None,
+            paste_mode: PasteMode::Or,
+        }
+    }
+
+    pub fn cursor(&self) -> Coord<i64> {
+        self.cursor
+    }
+
+    pub fn mark(&self) -> Option<Coord<i64>> {
+        self.mark
+    }
+
+    pub fn mode(&self) -> String {
+        let pen = match self.state {
+            _ if self.erase => "erase".to_string(),
+            State::ALIVE => "draw".to_string(),
+            State(s) => format!("draw {}", s),
+        };
+        match &self.clipboard {
+            Some(r) => format!("Edit ({}, {}x{} {})", pen, r.width(), r.height(), self.paste_mode.name()),
+            None => format!("Edit ({})", pen),
+        }
+    }
+
+    fn paint(&self, grid: &mut dyn Grid<i64>, cells: Vec<Coord<i64>>) {
+        for c in cells {
+            if self.erase {
+                grid.kill(c);
+            } else {
+                grid.set(c, self.state);
+            }
+        }
+    }
+
+    // Returns false when the user leaves edit mode
+    pub fn handle(&mut self, ch: Input, grid: &mut dyn Grid<i64>) -> bool {
+        match ch {
+            Input::KeyLeft => self.cursor.0 -= 1,
+            Input::KeyRight => self.cursor.0 += 1,
+            Input::KeyUp => self.cursor.1 -= 1,
+            Input::KeyDown => self.cursor.1 += 1,
+            Input::Character(' ') => {
+                if grid.get(self.cursor) == Some(self.state) {
+                    grid.kill(self.cursor);
+                } else {
+                    grid.set(self.cursor, self.state);
+                }
+            },
+            Input::Character(c @ '1'..='9') => {
+                let s = c as u8 - b'0';
+                if s < grid.rule().states() {
+                    self.state = State(s);
+                }
+            },
+            Input::Character('m') => self.mark = Some(self.cursor),
+            Input::Character('x') => self.erase = !self.erase,
+            Input::Character('l') => {
+                if let Some(mark) = self.mark {
+                    self.paint(grid, shapes::line(mark, self.cursor));
+                }
+            },
+            Input::Character('r') => {
+                if let Some(mark) = self.mark {
+                    self.paint(grid, shapes::rect(mark, self.cursor));
+                }
+            },
+            Input::Character('c') => {
+                if let Some(mark) = self.mark {
+                    self.clipboard = Some(Region::copy(grid, mark, self.cursor));
+                }
+            },
+            Input::Character('d') => {
+                if let Some(mark) = self.mark {
+                    self.clipboard = Some(Region::cut(grid, mark, self.cursor));
+                }
+            },
+            Input::Character('p') => {
+                if let Some(r) = &self.clipboard {
+                    r.paste(grid, self.cursor, self.paste_mode);
+                }
+            },
+            Input::Character('t') => self.clipboard.iter_mut().for_each(|r| r.rotate()),
+            Input::Character('h') => self.clipboard.iter_mut().for_each(|r| r.flip_h()),
+            Input::Character('v') => self.clipboard.iter_mut().for_each(|r| r.flip_v()),
+            Input::Character('w') => self.paste_mode = self.paste_mode.next(),
+            Input::Character('e') | Input::Character('\x1b') => return false,
+            _ => (),
+        }
+        true
+    }
+}
@@ -0,0 +1,565 @@
+// use std::collections::{HashMap, hash_map::{Entry, Keys}};
+// use std::thread::sleep;
+use std::time::{Duration, SystemTime};
+use std::ops::{Add, AddAssign, Sub};
+use std::hash::Hash;
+use std::cmp::{min, max};
+use pancurses::*;
+
+use life::array::ArrayGrid;
+use life::coord::Coord;
+use life::grid::Grid;
+use life::hashlife::HashLife;
+use life::history::History;
+use life::map::Map;
+use life::neighborhood::Neighborhood;
+use life::pattern::{Pattern, read_rle, read_cells, write_rle, write_cells};
+use life::period::{Period, PeriodDetector};
+use life::rule::Rule;
+use life::state::State;
+use life::tiles::TileGrid;
+use life::topology::Extent;
+
+mod browser;
+mod editor;
+mod panel;
+
+use browser::browse;
+use editor::Editor;
+
+const INIT: &[&str] = &[
+    // "X X",
+    // " XX",
+    // " X ",
+
+    // " XX",
+    // "XX ",
+    // " X ",
+
+    " X     ",
+    "   X   ",
+    "XX  XXX",
+
+    // "                        X           ",
+    // "                      X X           ",
+    // "            XX      XX            XX",
+    // "           X   X    XX            XX",
+    // "XX        X     X   XX              ",
+    // "XX        X   X XX    X X           ",
+    // "          X     X       X           ",
+    // "           X   X                    ",
+    // "            XX                      ",
+];
+
+type BaseType = i64;
+
+
+// Alive cells are 'O', the refractory states of Generations rules fade out from 'o' to '.'.
+// States of rule tables are shown by their letters in RLE: 'A' for 1, 'B' for 2 and so on.
+fn cell_char(state: Option<State>, rule: &Rule) -> char {
+    const FADE: &[char] = &['o', '+', ':', '.'];
+    let states = rule.states();
+    match state {
+        None => ' ',
+        Some(State(s)) if rule.table().is_some() => if s <= 24 { (b'A' + s - 1) as char } else { '#' },
+        Some(State::ALIVE) => 'O',
+        Some(State(s)) => {
+            let i = (s as usize - 2) * FADE.len() / max(states as usize - 2, 1);
+            FADE[min(i, FADE.len() - 1)]
+        },
+    }
+}
+
+struct Viewport<'a, T: Copy> {
+    win: &'a pancurses::Window,
+    origin: Coord<T>,
+    size: Coord<T>,
+    cursor: Option<Coord<T>>,
+    mark: Option<Coord<T>>,
+    hex: bool,
+    // Color attributes of the states
+    colors: Vec<chtype>,
+}
+
+impl<T> Viewport<'_, T> where
+    i32: TryFrom<T>,
+    T: From<i32>,
+    T: Add<Output = T>,
+    T: Sub<Output = T>,
+    T: PartialOrd,
+    T: Copy,
+    T: AddAssign,
+    T: Eq,
+    T: Hash,
+    T: Ord,
+    T: std::fmt::Display,
+{
+    pub fn new(win: &pancurses::Window) -> Viewport<'_, T> {
+        let mx = win.get_max_x();
+        let my = win.get_max_y();
+        Viewport {
+            win,
+            origin: Coord((-mx / 2).into(), (-my / 2).into()),
+            size: Coord(mx.into(), my.into()),
+            cursor: None,
+            mark: None,
+            hex: false,
+            colors: Vec::new(),
+        }
+    }
+
+    // On the hexagonal grid cells are two columns apart and each row is shifted by half a cell
+    // to the left of the one above it. The colors of a rule table are approximated by the
+    // nearest of the eight basic colors, see init_colors.
+    pub fn set_rule(&mut self, rule: &Rule) {
+        self.hex = rule.neighborhood() == Neighborhood::Hexagonal;
+        self.colors = (0..rule.states()).map(|s| match rule.table().and_then(|t| t.color(s)) {
+            Some((r, g, b)) if has_colors() => {
+                let color = (r >= 128) as chtype | ((g >= 128) as chtype) << 1 | ((b >= 128) as chtype) << 2;
+                if color == 0 { 0 } else { COLOR_PAIR(color) }
+            },
+            _ => 0,
+        }).collect();
+    }
+
+    fn cell(&self, state: Option<State>, rule: &Rule) -> chtype {
+        let color = state.and_then(|s| self.colors.get(s.0 as usize)).copied().unwrap_or(0);
+        cell_char(state, rule).to_chtype() | color
+    }
+
+    pub fn is_hex(&self) -> bool {
+        self.hex
+    }
+
+    // Screen column and row of the cell, which may be outside of the viewport
+    fn screen(&self, c: Coord<T>) -> Option<(i32, i32)> {
+        let x = i32::try_from(c.0 - self.origin.0).ok()?;
+        let y = i32::try_from(c.1 - self.origin.1).ok()?;
+        if self.hex {
+            Some((x.checked_mul(2)?.checked_sub(y)?, y))
+        } else {
+            Some((x, y))
+        }
+    }
+
+    fn put<C: ToChtype>(&self, c: Coord<T>, ch: C) {
+        let (w, h) = (i32::try_from(self.size.0).ok().unwrap(), i32::try_from(self.size.1).ok().unwrap());
+        if let Some((x, y)) = self.screen(c) {
+            if x >= 0 && x <= w && y >= 0 && y <= h {
+                self.win.mvaddch(y, x, ch);
+            }
+        }
+    }
+
+    // Draws the frame around a bounded universe, only the part that is inside the viewport
+    // Unbounded dimensions have no edges, the infinite plane none at all
+    fn render_boundary(&self, xs: Extent<T>, ys: Extent<T>) {
+        let (vul, vlr) = self.corners();
+        let (ul, lr) = (Coord(xs.map_or(vul.0, |e| e.0), ys.map_or(vul.1, |e| e.0)), Coord(xs.map_or(vlr.0, |e| e.1), ys.map_or(vlr.1, |e| e.1)));
+        let (left, top) = (ul.0 - 1.into(), ul.1 - 1.into());
+        let (right, bottom) = (lr.0 + 1.into(), lr.1 + 1.into());
+
+        // The left and right edges of the hexagonal grid are slanted
+        let vline = if self.hex { '/'.to_chtype() } else { ACS_VLINE() };
+        if ys.is_some() {
+            let mut x = max(ul.0, vul.0);
+            while x <= min(lr.0, vlr.0) {
+                self.put(Coord(x, top), ACS_HLINE());
+                self.put(Coord(x, bottom), ACS_HLINE());
+                x += 1.into();
+            }
+        }
+        if xs.is_some() {
+            let mut y = max(ul.1, vul.1);
+            while y <= min(lr.1, vlr.1) {
+                self.put(Coord(left, y), vline);
+                self.put(Coord(right, y), vline);
+                y += 1.into();
+            }
+        }
+        if xs.is_some() && ys.is_some() {
+            self.put(Coord(left, top), ACS_ULCORNER());
+            self.put(Coord(right, top), ACS_URCORNER());
+            self.put(Coord(left, bottom), ACS_LLCORNER());
+            self.put(Coord(right, bottom), ACS_LRCORNER());
+        }
+    }
+
+    pub fn render(&self, grid: &dyn Grid<T>, mode: &str, period: Option<Period<T>>) {
+        self.win.erase();
+        let (xs, ys) = grid.rule().topology().extents::<T>();
+        self.render_boundary(xs, ys);
+        let (ul, lr) = self.corners();
+        let rule = grid.rule();
+        for c in grid.cells_in(ul, lr) {
+            self.put(c, self.cell(grid.get(c), &rule));
+        }
+        if let Some(mark) = self.mark {
+            self.put(mark, '+');
+        }
+        if let Some(cursor) = self.cursor {
+            self.put(cursor, self.cell(grid.get(cursor), &rule) | A_REVERSE);
+        }
+        let mut status = format!("{} Turn: {} Cells: {} Rule: {}", mode, grid.generation(), grid.population(), grid.rule());
+        if let Some(exp) = grid.step_exp() {
+            status += &format!(" Step: 2^{}", exp);
+        }
+        if let Some(period) = period {
+            status += &format!(" [{}]", period);
+        }
+        self.win.mvaddstr(i32::try_from(self.size.1).ok().unwrap() - 1, 0, status);
+        self.win.refresh();
+    }
+
+    // Upper left and lower right cells covering the viewport. The skewed hexagonal layout
+    // needs wider range of columns, not all of them are visible in every row.
+    pub fn corners(&self) -> (Coord<T>, Coord<T>) {
+        if self.hex {
+            let (w, h) = (i32::try_from(self.size.0).ok().unwrap(), i32::try_from(self.size.1).ok().unwrap());
+            (self.origin, self.origin.offset(Coord(((w + h) / 2 + 1).into(), self.size.1)))
+        } else {
+            (self.origin, self.origin.offset(self.size))
+        }
+    }
+
+    pub fn mv(&mut self, x: T, y: T) { // TODO: Refactor to accept Coord<T>
+        self.origin.0 += x;
+        self.origin.1 += y;
+    }
+
+    pub fn mvto(&mut self, x: T, y: T) { // TODO: Refactor to accept Coord<T>
+        self.origin.0 = x;
+        self.origin.1 = y;
+    }
+
+    pub fn getsize(&self) -> (T, T) {
+        (self.size.0, self.size.1)
+    }
+
+    pub fn set_cursor(&mut self, cursor: Option<Coord<T>>, mark: Option<Coord<T>>) {
+        self.cursor = cursor;
+        self.mark = mark;
+    }
+
+    // Scrolls the viewport so that the cell is on the screen, above the status line
+    pub fn follow(&mut self, c: Coord<T>) {
+        let one: T = 1.into();
+        let two: T = 2.into();
+        if c.0 < self.origin.0 {
+            self.origin.0 = c.0;
+        } else if c.0 > self.origin.0 + self.size.0 - one {
+            self.origin.0 = c.0 - self.size.0 + one;
+        }
+        if c.1 < self.origin.1 {
+            self.origin.1 = c.1;
+        } else if c.1 > self.origin.1 + self.size.1 - two {
+            self.origin.1 = c.1 - self.size.1 + two;
+        }
+        if self.hex {
+            // Moving along a row may scroll the slanted columns out of the screen
+            let w = i32::try_from(self.size.0).ok().unwrap();
+            while self.screen(c).is_some_and(|(x, _)| x < 0) {
+                self.origin.0 = self.origin.0 - one;
+            }
+            while self.screen(c).is_some_and(|(x, _)| x > w - 1) {
+                self.origin.0 += one;
+            }
+        }
+    }
+}
+
+#[derive(Copy, Clone, PartialEq)]
+enum EngineKind {
+    Sparse,
+    Tiles,
+    Array(BaseType, BaseType),
+    HashLife,
+}
+
+fn center_viewport(grid: &dyn Grid<BaseType>, viewport: &mut Viewport<BaseType>) {
+    if grid.population() == 0 {
+        return;
+    }
+    let (ul, lr) = grid.dims();
+    let (sx, sy) = viewport.getsize();
+    let x0 = (lr.0 - ul.0) / 2 + ul.0 - if viewport.is_hex() { (sx + sy) / 4 } else { sx / 2 };
+    let y0 = (lr.1 - ul.1) / 2 + ul.1 - sy / 2;
+    viewport.mvto(x0, y0);
+
+}
+
+struct Options {
+    rule: Rule,
+    engine: EngineKind,
+    threads: usize,
+    history: usize,
+}
+
+fn parse_args() -> Result<Options, String> {
+    let mut opts = Options { rule: Rule::default(), engine: EngineKind::Sparse, threads: 1, history: 256 };
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "-r" | "--rule" => {
+                let r = args.next().ok_or(format!("Missing argument for {}", arg))?;
+                opts.rule = r.parse()?;
+            },
+            "-e" | "--engine" => {
+                let e = args.next().ok_or(format!("Missing argument for {}", arg))?;
+                opts.engine = match e.as_str() {
+                    "sparse" => EngineKind::Sparse,
+                    "tiles" => EngineKind::Tiles,
+                    "hashlife" => EngineKind::HashLife,
+                    // Sizes have to be positive, the array is allocated right away
+                    _ => match e.strip_prefix("array:").and_then(|size| size.split_once('x')) {
+                        Some((w, h)) => EngineKind::Array(
+                            w.parse().ok().filter(|w| *w > 0).ok_or(format!("Invalid width '{}'", w))?,
+                            h.parse().ok().filter(|h| *h > 0).ok_or(format!("Invalid height '{}'", h))?,
+                        ),
+                        None => return Err(format!("Unknown engine '{}'", e)),
+                    },
+                };
+            },
+            "--history" => {
+                let h = args.next().ok_or(format!("Missing argument for {}", arg))?;
+                opts.history = h.parse().map_err(|_| format!("Invalid history size '{}'", h))?;
+            },
+            "-t" | "--threads" => {
+                let t = args.next().ok_or(format!("Missing argument for {}", arg))?;
+                opts.threads = t.parse().map_err(|_| format!("Invalid thread count '{}'", t))?;
+            },
+            _ => return Err(format!("Unknown argument '{}'", arg)),
+        }
+    }
+    Ok(opts)
+}
+
+// The rule and the position given by the pattern file take precedence over the command line
+fn load(opts: &Options, pattern: &Pattern) -> Result<Box<dyn Grid<BaseType>>, String> {
+    let mut grid: Box<dyn Grid<BaseType>> = match opts.engine {
+        EngineKind::Sparse => Box::new(Map::new()),
+        EngineKind::Tiles => Box::new(TileGrid::new()),
+        EngineKind::Array(w, h) => Box::new(ArrayGrid::new(w, h)),
+        EngineKind::HashLife => Box::new(HashLife::new()),
+    };
+    grid.set_rule(pattern.rule.clone().unwrap_or_else(|| opts.rule.clone()))?;
+    grid.set_threads(opts.threads);
+    let offset = pattern.offset.unwrap_or(Coord(0, 0));
+    // States the rule does not have are taken as alive
+    let states = grid.rule().states();
+    for (c, s) in &pattern.cells {
+        grid.set(c.offset(offset), if s.0 < states { *s } else { State::ALIVE });
+    }
+    Ok(grid)
+}
+
+// Color pair N draws the basic color N (red = 1, green = 2, blue = 4 and their mixes) on
+// the default background
+fn init_colors() {
+    if has_colors() {
+        start_color();
+        use_default_colors();
+        for color in 1..8 {
+            init_pair(color, color, -1);
+        }
+    }
+}
+
+// Reads a line of input on the status line, returns None if cancelled with Escape
+fn prompt(win: &Window, msg: &str) -> Option<String> {
+    let y = win.get_max_y() - 1;
+    let mut input = String::new();
+    win.nodelay(false);
+    curs_set(1);
+    let res = loop {
+        win.mv(y, 0);
+        win.clrtoeol();
+        win.addstr(format!("{}{}", msg, input));
+        win.refresh();
+        match win.getch() {
+            Some(Input::Character('\n')) => break Some(input),
+            Some(Input::Character('\x1b')) => break None,
+            Some(Input::KeyBackspace) | Some(Input::Character('\x7f')) | Some(Input::Character('\x08')) => {
+                input.pop();
+            },
+            Some(Input::Character(c)) if !c.is_control() => input.push(c),
+            _ => (),
+        }
+    };
+    curs_set(0);
+    win.nodelay(true);
+    res
+}
+
+fn main() {
+    // The file browser changes the current directory, rule files are looked up by name in
+    // the one the program started in
+    if let Ok(dir) = std::env::current_dir() {
+        life::table::set_rule_dirs(vec![dir]);
+    }
+    let (opts, mut pattern, mut grid) = match parse_args().and_then(|opts| {
+        let pattern = Pattern::new(INIT);
+        let grid = load(&opts, &pattern)?;
+        Ok((opts, pattern, grid))
+    }) {
+        Ok(res) => res,
+        Err(e) => {
+            eprintln!("{}", e);
+            eprintln!("Usage: life [-r|--rule RULE] [-e|--engine sparse|tiles|hashlife|array:WxH] [-t|--threads N] [--history N]");
+            std::process::exit(1);
+        }
+    };
+
+    let win = initscr();
+    init_colors();
+    curs_set(0);
+    win.nodelay(true);
+    win.keypad(true);
+
+    let mut viewport: Viewport<BaseType> = Viewport::new(&win);
+    viewport.set_rule(&grid.rule());
+    if viewport.is_hex() {
+        // The skew moves the initial pattern away from where it is placed on the square grid
+        center_viewport(grid.as_ref(), &mut viewport);
+    }
+
+    let mut delay = Duration::from_millis(128);
+    let mut do_delay = true;
+    let mut last_now = SystemTime::now();
+    let mut history: History<BaseType> = History::new(opts.history);
+    let mut period: PeriodDetector<BaseType> = PeriodDetector::new();
+    let mut paused = false;
+    let mut editor: Option<Editor> = None;
+
+    loop {
+        let now = SystemTime::now();
+
+        if paused {
+            // Nothing to compute, don't spin at full speed waiting for input
+            std::thread::sleep(Duration::from_millis(10));
+        } else if !do_delay || now.duration_since(last_now).unwrap_or(Duration::from_millis(0)) > delay {
+            last_now = now;
+            history.record(grid.as_ref());
+            grid.step();
+            period.check(grid.as_ref());
+        }
+
+        let mode = match &editor {
+            Some(ed) => ed.mode(),
+            None => String::from(if paused { "Paused" } else { "Running" }),
+        };
+        viewport.render(grid.as_ref(), &mode, period.period());
+
+        let input = win.getch();
+        if let (Some(ch), Some(ed)) = (input, &mut editor) {
+            // Any edit may break the repetition
+            period.clear();
+            if ed.handle(ch, grid.as_mut()) {
+                viewport.follow(ed.cursor());
+                viewport.set_cursor(Some(ed.cursor()), ed.mark());
+            } else {
+                editor = None;
+                viewport.set_cursor(None, None);
+            }
+        } else if let Some(ch) = input {
+            match ch {
+                Input::KeyLeft => viewport.mv(-3, 0),
+                Input::KeyUp => viewport.mv(0, -3),
+                Input::KeyRight => viewport.mv(3, 0),
+                Input::KeyDown => viewport.mv(0, 3),
+                Input::Character(c) => {
+                    if c == 'q' {
+                        break;
+                    } else if c == ' ' {
+                        paused = !paused;
+                    } else if c == 'e' {
+                        let (ul, lr) = viewport.corners();
+                        let ed = Editor::new(Coord((ul.0 + lr.0) / 2, (ul.1 + lr.1) / 2));
+                        viewport.set_cursor(Some(ed.cursor()), ed.mark());
+                        editor = Some(ed);
+                        paused = true;
+                    } else if c == 'n' {
+                        history.record(grid.as_ref());
+                        grid.advance(1);
+                        period.check(grid.as_ref());
+                    } else if c == 'N' {
+                        if let Some(n) = prompt(&win, "Advance by generations: ").and_then(|s| s.trim().parse().ok()) {
+                            history.record(grid.as_ref());
+                            grid.advance(n);
+                            period.check(grid.as_ref());
+                        }
+                    } else if c == 'b' {
+                        history.back(grid.as_mut());
+                        period.clear();
+                    } else if c == 'r' {
+                        history.rewind(grid.as_mut());
+                        period.clear();
+                    } else if c == 'g' {
+                        if let Some(generation) = prompt(&win, "Jump to generation: ").and_then(|s| s.trim().parse().ok()) {
+                            if let Err(e) = history.jump(grid.as_mut(), generation) {
+                                panel::message(&win, "Cannot jump", &e);
+                            }
+                            period.clear();
+                        }
+                    } else if c == 'd' {
+                        do_delay = !do_delay;
+                    } else if c == '-' {
+                        if let Some(exp) = grid.step_exp() {
+                            grid.set_step_exp(exp.saturating_sub(1));
+                        } else {
+                            delay *= 2;
+                        }
+                    } else if c == '+' {
+                        if let Some(exp) = grid.step_exp() {
+                            grid.set_step_exp(exp + 1);
+                        } else if delay.as_millis() > 1 {
+                            delay /= 2;
+                        }
+                    } else if c == 'o' {
+                        if let Some(path) = browse(&win, false) {
+                            let name = path.to_string_lossy().to_lowercase();
+                            let res = if name.ends_with(".rle") {
+                                read_rle(&path)
+                            } else if name.ends_with(".cells") {
+                                read_cells(&path)
+                            } else {
+                                continue;
+                            };
+                            match res.map_err(|e| e.to_string()).and_then(|p| Ok((load(&opts, &p)?, p))) {
+                                Ok((g, p)) => {
+                                    grid = g;
+                                    pattern = p;
+                                    history.clear();
+                                    period.clear();
+                                    viewport.set_rule(&grid.rule());
+                                    center_viewport(grid.as_ref(), &mut viewport);
+                                    panel::info(&win, &pattern);
+                                },
+                                Err(e) => panel::message(&win, &format!("Cannot load {}", path.to_string_lossy()), &e),
+                            }
+                        }
+                    } else if c == 'i' {
+                        panel::info(&win, &pattern);
+                    } else if c == 's' {
+                        if let Some(path) = browse(&win, true) {
+                            if pattern.name.is_none() {
+                                pattern.name = path.file_stem().map(|s| s.to_string_lossy().into_owned());
+                            }
+                            let res = if path.to_string_lossy().to_lowercase().ends_with(".cells") {
+                                write_cells(&path, grid.as_ref(), &pattern)
+                            } else {
+                                write_rle(&path, grid.as_ref(), &pattern)
+                            };
+                            if let Err(e) = res {
+                                panel::message(&win, &format!("Cannot save {}", path.to_string_lossy()), &e.to_string());
+                            }
+                        }
+                    }
+                },
+                _ => ()
+            }
+        }
+    }
+
+    endwin();
+}
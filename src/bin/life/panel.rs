@@ -0,0 +1,73 @@
+use pancurses::*;
+
+use life::pattern::Pattern;
+
+// Splits the text into lines no longer than the width, breaking at spaces where possible
+fn wrap(text: &str, width: usize) -> Vec<String> {
+    let mut lines = vec![String::new()];
+    for word in text.split_whitespace() {
+        let mut word = word.to_string();
+        while word.chars().count() > width {
+            let rest = word.split_off(word.char_indices().nth(width).unwrap().0);
+            lines.push(word);
+            word = rest;
+        }
+        let last = lines.last_mut().unwrap();
+        if last.is_empty() {
+            *last = word;
+        } else if last.chars().count() + 1 + word.chars().count() <= width {
+            *last += " ";
+            *last += &word;
+        } else {
+            lines.push(word);
+        }
+    }
+    lines
+}
+
+// Shows the lines in a framed window in the middle of the screen until a key is pressed
+fn show(win: &Window, title: &str, lines: &[String]) {
+    let width = win.get_max_x() * 3 / 4;
+    let text_width = (width - 4) as usize;
+    let text: Vec<String> = lines.iter().flat_map(|l| wrap(l, text_width)).collect();
+    let height = std::cmp::min(text.len() as i32 + 2, win.get_max_y() - 2);
+
+    let pwin = win.subwin(height, width, (win.get_max_y() - height) / 2, (win.get_max_x() - width) / 2)
+        .expect("Cannot create subwindow");
+    pwin.erase();
+    pwin.attron(A_ALTCHARSET);
+    pwin.border(ACS_VLINE(), ACS_VLINE(), ACS_HLINE(), ACS_HLINE(), ACS_ULCORNER(), ACS_URCORNER(), ACS_LLCORNER(), ACS_LRCORNER());
+    pwin.attroff(A_ALTCHARSET);
+    pwin.mvaddstr(0, 4, format!(" {} ", title));
+    for (i, l) in text.iter().take((height - 2) as usize).enumerate() {
+        pwin.mvaddstr(i as i32 + 1, 2, l);
+    }
+    pwin.refresh();
+
+    win.nodelay(false);
+    win.getch();
+    win.nodelay(true);
+    pwin.delwin();
+}
+
+pub fn message(win: &Window, title: &str, text: &str) {
+    show(win, title, &[text.to_string()]);
+}
+
+pub fn info(win: &Window, pattern: &Pattern) {
+    let mut lines = Vec::new();
+    if let Some(author) = &pattern.author {
+        lines.push(format!("Author: {}", author));
+    }
+    if let Some(url) = &pattern.url {
+        lines.push(format!("URL: {}", url));
+    }
+    if !lines.is_empty() && !pattern.comments.is_empty() {
+        lines.push(String::new());
+    }
+    lines.extend(pattern.comments.iter().cloned());
+    if lines.is_empty() {
+        lines.push(String::from("No description"));
+    }
+    show(win, pattern.name.as_deref().unwrap_or("Untitled"), &lines);
+}
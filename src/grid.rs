@@ -0,0 +1,68 @@
+use crate::coord::Coord;
+use crate::rule::Rule;
+use crate::state::State;
+use crate::stats::Stats;
+
+// Common interface of the cell storage backends
+pub trait Grid<T: Copy + PartialOrd> {
+    fn get(&self, coord: Coord<T>) -> Option<State>;
+    fn set(&mut self, coord: Coord<T>, s: State);
+    fn kill(&mut self, coord: Coord<T>);
+    fn iter(&self) -> Box<dyn Iterator<Item = Coord<T>> + '_>;
+    fn clear(&mut self);
+
+    // Bounding box of the alive cells. Panics if the grid is empty
+    fn dims(&self) -> (Coord<T>, Coord<T>);
+
+    fn rule(&self) -> Rule;
+    // Fails if the backend cannot run the rule, e.g. because of its topology
+    fn set_rule(&mut self, rule: Rule) -> Result<(), String>;
+    fn generation(&self) -> u64;
+    fn set_generation(&mut self, generation: u64);
+    fn population(&self) -> u64;
+    fn step(&mut self) -> Stats;
+
+    fn step_n(&mut self, n: u64) -> Stats {
+        let mut total = Stats { population: self.population(), ..Stats::default() };
+        for _ in 0..n {
+            let stats = self.step();
+            total.births += stats.births;
+            total.deaths += stats.deaths;
+            total.population = stats.population;
+        }
+        total
+    }
+
+    // Advances exactly by the given number of generations, regardless of the step size
+    fn advance(&mut self, generations: u64) -> Stats {
+        let exp = match self.step_exp() {
+            Some(exp) => exp,
+            None => return self.step_n(generations),
+        };
+        let mut total = Stats { population: self.population(), ..Stats::default() };
+        for bit in (0..64).rev().filter(|b| generations & (1 << b) != 0) {
+            self.set_step_exp(bit);
+            let stats = self.step();
+            total.births += stats.births;
+            total.deaths += stats.deaths;
+            total.population = stats.population;
+        }
+        self.set_step_exp(exp);
+        total
+    }
+
+    // Alive cells inside the rectangle, both corners inclusive
+    fn cells_in(&self, ul: Coord<T>, lr: Coord<T>) -> Vec<Coord<T>> {
+        self.iter().filter(|c| c.0 >= ul.0 && c.0 <= lr.0 && c.1 >= ul.1 && c.1 <= lr.1).collect()
+    }
+
+    // Backends advancing several generations per step report the step size as a power of two
+    fn step_exp(&self) -> Option<u8> {
+        None
+    }
+
+    fn set_step_exp(&mut self, _exp: u8) {}
+
+    // Backends able to compute a generation in parallel use up to that many threads
+    fn set_threads(&mut self, _threads: usize) {}
+}
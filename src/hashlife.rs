@@ -0,0 +1,438 @@
+use std::collections::HashMap;
+use std::cmp::{min, max};
+
+use crate::coord::Coord;
+use crate::grid::Grid;
+use crate::map::Map;
+use crate::rule::Rule;
+use crate::state::State;
+use crate::stats::Stats;
+
+type NodeId = u32;
+
+const DEAD: NodeId = 0;
+const ALIVE: NodeId = 1;
+
+const MIN_LEVEL: u8 = 3;
+const MAX_LEVEL: u8 = 62;
+const GC_THRESHOLD: usize = 1 << 22;
+
+// Quadtree node. Level 0 nodes are single cells, level k nodes are 2^k x 2^k squares
+#[derive(Copy, Clone)]
+struct Node {
+    nw: NodeId,
+    ne: NodeId,
+    sw: NodeId,
+    se: NodeId,
+    level: u8,
+    population: u64,
+}
+
+// Hashlife universe. The root node is always centered at (0, 0), so a root of level k
+// covers [-2^(k-1), 2^(k-1)) in both directions.
+pub struct HashLife {
+    nodes: Vec<Node>,
+    index: HashMap<(NodeId, NodeId, NodeId, NodeId), NodeId>,
+    results: HashMap<(NodeId, u8), NodeId>,
+    empty: Vec<NodeId>,
+    root: NodeId,
+    rule: Rule,
+    step_exp: u8,
+    generation: u64,
+}
+
+impl Default for HashLife {
+    fn default() -> HashLife {
+        HashLife::new()
+    }
+}
+
+impl HashLife {
+    pub fn new() -> HashLife {
+        let mut hl = HashLife {
+            nodes: Vec::new(),
+            index: HashMap::new(),
+            results: HashMap::new(),
+            empty: Vec::new(),
+            root: DEAD,
+            rule: Rule::default(),
+            step_exp: 0,
+            generation: 0,
+        };
+        hl.init_leaves();
+        hl.root = hl.empty(MIN_LEVEL);
+        hl
+    }
+
+    pub fn from_map(map: &Map<i64>) -> Result<HashLife, String> {
+        let mut hl = HashLife::new();
+        hl.set_rule(map.rule())?;
+        for c in map.iter() {
+            hl.set(c, State::ALIVE);
+        }
+        Ok(hl)
+    }
+
+    fn init_leaves(&mut self) {
+        let leaf = |population| Node { nw: DEAD, ne: DEAD, sw: DEAD, se: DEAD, level: 0, population };
+        self.nodes.push(leaf(0));
+        self.nodes.push(leaf(1));
+        self.empty.push(DEAD);
+    }
+
+    fn node(&self, id: NodeId) -> Node {
+        self.nodes[id as usize]
+    }
+
+    fn join(&mut self, nw: NodeId, ne: NodeId, sw: NodeId, se: NodeId) -> NodeId {
+        if let Some(id) = self.index.get(&(nw, ne, sw, se)) {
+            return *id;
+        }
+        let n = self.node(nw);
+        let population = n.population + self.node(ne).population + self.node(sw).population + self.node(se).population;
+        let id = self.nodes.len() as NodeId;
+        self.nodes.push(Node { nw, ne, sw, se, level: n.level + 1, population });
+        self.index.insert((nw, ne, sw, se), id);
+        id
+    }
+
+    fn empty(&mut self, level: u8) -> NodeId {
+        while self.empty.len() <= level as usize {
+            let e = *self.empty.last().unwrap();
+            let id = self.join(e, e, e, e);
+            self.empty.push(id);
+        }
+        self.empty[level as usize]
+    }
+
+    fn level(&self) -> u8 {
+        self.node(self.root).level
+    }
+
+    fn half(&self) -> i64 {
+        1 << (self.level() - 1)
+    }
+
+    fn contains(&self, coord: Coord<i64>) -> bool {
+        let half = self.half();
+        coord.0 >= -half && coord.0 < half && coord.1 >= -half && coord.1 < half
+    }
+
+    fn expand(&mut self) {
+        let r = self.node(self.root);
+        let e = self.empty(r.level - 1);
+        let nw = self.join(e, e, e, r.nw);
+        let ne = self.join(e, e, r.ne, e);
+        let sw = self.join(e, r.sw, e, e);
+        let se = self.join(r.se, e, e, e);
+        self.root = self.join(nw, ne, sw, se);
+    }
+
+    fn centre(&mut self, id: NodeId) -> NodeId {
+        let n = self.node(id);
+        let (nw, ne, sw, se) = (self.node(n.nw), self.node(n.ne), self.node(n.sw), self.node(n.se));
+        self.join(nw.se, ne.sw, sw.ne, se.nw)
+    }
+
+    fn set_rec(&mut self, id: NodeId, x: i64, y: i64, alive: bool) -> NodeId {
+        let n = self.node(id);
+        if n.level == 0 {
+            return if alive { ALIVE } else { DEAD };
+        }
+        let half = 1 << (n.level - 1);
+        let (mut nw, mut ne, mut sw, mut se) = (n.nw, n.ne, n.sw, n.se);
+        match (x >= half, y >= half) {
+            (false, false) => nw = self.set_rec(nw, x, y, alive),
+            (true, false) => ne = self.set_rec(ne, x - half, y, alive),
+            (false, true) => sw = self.set_rec(sw, x, y - half, alive),
+            (true, true) => se = self.set_rec(se, x - half, y - half, alive),
+        }
+        self.join(nw, ne, sw, se)
+    }
+
+    fn set_cell(&mut self, coord: Coord<i64>, alive: bool) {
+        while !self.contains(coord) {
+            if self.level() >= MAX_LEVEL {
+                panic!("Coordinate is out of Hashlife universe bounds");
+            }
+            self.expand();
+        }
+        let half = self.half();
+        self.root = self.set_rec(self.root, coord.0 + half, coord.1 + half, alive);
+    }
+
+    // Brute force one generation of the 2x2 centre of a 4x4 node
+    fn base(&mut self, id: NodeId) -> NodeId {
+        let n = self.node(id);
+        let mut cells = [[false; 4]; 4];
+        for (qx, qy, q) in [(0, 0, n.nw), (2, 0, n.ne), (0, 2, n.sw), (2, 2, n.se)] {
+            let q = self.node(q);
+            cells[qy][qx] = q.nw == ALIVE;
+            cells[qy][qx + 1] = q.ne == ALIVE;
+            cells[qy + 1][qx] = q.sw == ALIVE;
+            cells[qy + 1][qx + 1] = q.se == ALIVE;
+        }
+
+        let mut res = [DEAD; 4];
+        for (i, (x, y)) in [(1, 1), (2, 1), (1, 2), (2, 2)].into_iter().enumerate() {
+            let mut nc = 0;
+            for dx in 0..3 {
+                for dy in 0..3 {
+                    if !(dx == 1 && dy == 1) && cells[y + dy - 1][x + dx - 1] {
+                        nc += 1;
+                    }
+                }
+            }
+            if self.rule.next(cells[y][x], nc) {
+                res[i] = ALIVE;
+            }
+        }
+        self.join(res[0], res[1], res[2], res[3])
+    }
+
+    // Returns the centre of the node, advanced by 2^j generations. Requires j <= level - 2
+    fn step_rec(&mut self, id: NodeId, j: u8) -> NodeId {
+        let n = self.node(id);
+        if n.population == 0 {
+            return self.empty(n.level - 1);
+        }
+        if let Some(r) = self.results.get(&(id, j)) {
+            return *r;
+        }
+
+        let res = if n.level == 2 {
+            self.base(id)
+        } else {
+            let (nw, ne, sw, se) = (self.node(n.nw), self.node(n.ne), self.node(n.sw), self.node(n.se));
+            let n01 = self.join(nw.ne, ne.nw, nw.se, ne.sw);
+            let n10 = self.join(nw.sw, nw.se, sw.nw, sw.ne);
+            let n11 = self.join(nw.se, ne.sw, sw.ne, se.nw);
+            let n12 = self.join(ne.sw, ne.se, se.nw, se.ne);
+            let n21 = self.join(sw.ne, se.nw, sw.se, se.sw);
+            let nine = [n.nw, n01, n.ne, n10, n11, n12, n.sw, n21, n.se];
+
+            // Full speed steps advance both halves by 2^(j-1), slower ones only the second half
+            let full = j == n.level - 2;
+            let mut r = [DEAD; 9];
+            for (i, sub) in nine.into_iter().enumerate() {
+                r[i] = if full { self.step_rec(sub, j - 1) } else { self.centre(sub) };
+            }
+            let inner = if full { j - 1 } else { j };
+
+            let a = self.join(r[0], r[1], r[3], r[4]);
+            let b = self.join(r[1], r[2], r[4], r[5]);
+            let c = self.join(r[3], r[4], r[6], r[7]);
+            let d = self.join(r[4], r[5], r[7], r[8]);
+            let a = self.step_rec(a, inner);
+            let b = self.step_rec(b, inner);
+            let c = self.step_rec(c, inner);
+            let d = self.step_rec(d, inner);
+            self.join(a, b, c, d)
+        };
+
+        self.results.insert((id, j), res);
+        res
+    }
+
+    // Rebuilds the node store keeping only the nodes reachable from the root
+    pub fn gc(&mut self) {
+        let old = std::mem::take(&mut self.nodes);
+        self.index.clear();
+        self.results.clear();
+        self.empty.clear();
+        self.init_leaves();
+
+        let mut moved: HashMap<NodeId, NodeId> = HashMap::new();
+        moved.insert(DEAD, DEAD);
+        moved.insert(ALIVE, ALIVE);
+        self.root = self.copy_node(&old, self.root, &mut moved);
+    }
+
+    fn copy_node(&mut self, old: &[Node], id: NodeId, moved: &mut HashMap<NodeId, NodeId>) -> NodeId {
+        if let Some(new) = moved.get(&id) {
+            return *new;
+        }
+        let n = old[id as usize];
+        let nw = self.copy_node(old, n.nw, moved);
+        let ne = self.copy_node(old, n.ne, moved);
+        let sw = self.copy_node(old, n.sw, moved);
+        let se = self.copy_node(old, n.se, moved);
+        let new = self.join(nw, ne, sw, se);
+        moved.insert(id, new);
+        new
+    }
+
+    fn collect(&self, id: NodeId, x0: i64, y0: i64, rect: (Coord<i64>, Coord<i64>), out: &mut Vec<Coord<i64>>) {
+        let n = self.node(id);
+        let size = 1i64 << n.level;
+        let (ul, lr) = rect;
+        if n.population == 0 || x0 > lr.0 || y0 > lr.1 || x0 + size - 1 < ul.0 || y0 + size - 1 < ul.1 {
+            return;
+        }
+        if n.level == 0 {
+            out.push(Coord(x0, y0));
+            return;
+        }
+        let half = size / 2;
+        self.collect(n.nw, x0, y0, rect, out);
+        self.collect(n.ne, x0 + half, y0, rect, out);
+        self.collect(n.sw, x0, y0 + half, rect, out);
+        self.collect(n.se, x0 + half, y0 + half, rect, out);
+    }
+
+    fn bounds(&self, id: NodeId, memo: &mut HashMap<NodeId, (i64, i64, i64, i64)>) -> Option<(i64, i64, i64, i64)> {
+        let n = self.node(id);
+        if n.population == 0 {
+            return None;
+        }
+        if n.level == 0 {
+            return Some((0, 0, 0, 0));
+        }
+        if let Some(b) = memo.get(&id) {
+            return Some(*b);
+        }
+        let half = 1i64 << (n.level - 1);
+        let mut res: Option<(i64, i64, i64, i64)> = None;
+        for (dx, dy, q) in [(0, 0, n.nw), (half, 0, n.ne), (0, half, n.sw), (half, half, n.se)] {
+            if let Some(b) = self.bounds(q, memo) {
+                let b = (b.0 + dx, b.1 + dy, b.2 + dx, b.3 + dy);
+                res = Some(match res {
+                    Some(r) => (min(r.0, b.0), min(r.1, b.1), max(r.2, b.2), max(r.3, b.3)),
+                    None => b,
+                });
+            }
+        }
+        memo.insert(id, res.unwrap());
+        res
+    }
+}
+
+impl Grid<i64> for HashLife {
+    fn get(&self, coord: Coord<i64>) -> Option<State> {
+        if !self.contains(coord) {
+            return None;
+        }
+        let half = self.half();
+        let (mut x, mut y) = (coord.0 + half, coord.1 + half);
+        let mut n = self.node(self.root);
+        while n.level > 0 {
+            if n.population == 0 {
+                return None;
+            }
+            let half = 1 << (n.level - 1);
+            let child = match (x >= half, y >= half) {
+                (false, false) => n.nw,
+                (true, false) => n.ne,
+                (false, true) => n.sw,
+                (true, true) => n.se,
+            };
+            x %= half;
+            y %= half;
+            n = self.node(child);
+        }
+        if n.population > 0 { Some(State::ALIVE) } else { None }
+    }
+
+    fn set(&mut self, coord: Coord<i64>, _s: State) {
+        self.set_cell(coord, true);
+    }
+
+    fn kill(&mut self, coord: Coord<i64>) {
+        if self.contains(coord) {
+            self.set_cell(coord, false);
+        }
+    }
+
+    fn iter(&self) -> Box<dyn Iterator<Item = Coord<i64>> + '_> {
+        Box::new(self.cells_in(Coord(i64::MIN, i64::MIN), Coord(i64::MAX, i64::MAX)).into_iter())
+    }
+
+    fn clear(&mut self) {
+        self.root = self.empty(MIN_LEVEL);
+    }
+
+    fn dims(&self) -> (Coord<i64>, Coord<i64>) {
+        let half = self.half();
+        let b = self.bounds(self.root, &mut HashMap::new()).expect("Empty universe has no dimensions");
+        (Coord(b.0 - half, b.1 - half), Coord(b.2 - half, b.3 - half))
+    }
+
+    fn rule(&self) -> Rule {
+        self.rule.clone()
+    }
+
+    // Memoised results depend on the rule, so they have to be dropped
+    fn set_rule(&mut self, rule: Rule) -> Result<(), String> {
+        if !rule.is_plain_life_like() {
+            return Err("Hashlife supports only two-state totalistic Moore rules on the infinite plane, use the sparse map for others".to_string());
+        }
+        self.rule = rule;
+        self.results.clear();
+        Ok(())
+    }
+
+    fn generation(&self) -> u64 {
+        self.generation
+    }
+
+    fn set_generation(&mut self, generation: u64) {
+        self.generation = generation;
+    }
+
+    fn population(&self) -> u64 {
+        self.nodes[self.root as usize].population
+    }
+
+    fn step(&mut self) -> Stats {
+        let before = self.population();
+
+        // The pattern must fit into the inner half of the root so that nothing escapes the result
+        loop {
+            let centre = self.centre(self.root);
+            if self.level() >= self.step_exp + 2 && self.node(centre).population == self.population() {
+                break;
+            }
+            self.expand();
+        }
+        self.expand();
+
+        self.root = self.step_rec(self.root, self.step_exp);
+        self.generation += 1 << self.step_exp;
+
+        while self.level() > MIN_LEVEL {
+            let centre = self.centre(self.root);
+            if self.node(centre).population != self.population() {
+                break;
+            }
+            self.root = centre;
+        }
+
+        if self.nodes.len() > GC_THRESHOLD {
+            self.gc();
+        }
+
+        // Individual cells are not tracked, so births and deaths are net population changes
+        let after = self.population();
+        Stats {
+            births: after.saturating_sub(before),
+            deaths: before.saturating_sub(after),
+            population: after,
+        }
+    }
+
+    fn cells_in(&self, ul: Coord<i64>, lr: Coord<i64>) -> Vec<Coord<i64>> {
+        let mut out = Vec::new();
+        let half = self.half();
+        self.collect(self.root, -half, -half, (ul, lr), &mut out);
+        out
+    }
+
+    // Each call to step() advances the universe by 2^step_exp generations
+    fn step_exp(&self) -> Option<u8> {
+        Some(self.step_exp)
+    }
+
+    fn set_step_exp(&mut self, exp: u8) {
+        self.step_exp = min(exp, MAX_LEVEL - 3);
+    }
+}
@@ -0,0 +1,123 @@
+use std::collections::VecDeque;
+
+use crate::coord::Coord;
+use crate::grid::Grid;
+use crate::state::State;
+
+struct Snapshot<T: Copy> {
+    generation: u64,
+    cells: Vec<(Coord<T>, State)>,
+}
+
+impl<T: Copy + PartialOrd> Snapshot<T> {
+    fn take(grid: &dyn Grid<T>) -> Snapshot<T> {
+        Snapshot {
+            generation: grid.generation(),
+            cells: grid.iter().map(|c| (c, grid.get(c).unwrap())).collect(),
+        }
+    }
+
+    fn restore(&self, grid: &mut dyn Grid<T>) {
+        grid.clear();
+        for (c, s) in &self.cells {
+            grid.set(*c, *s);
+        }
+        grid.set_generation(self.generation);
+    }
+}
+
+// Larger generations are not recorded, copying them before each step would slow the run down
+const MAX_POPULATION: u64 = 100_000;
+// Cells kept in all the snapshots together, the oldest snapshots are dropped beyond that
+const MAX_CELLS: usize = 4_000_000;
+
+// Bounded list of past generations. The initial generation is always kept,
+// so it is possible to rewind even after the oldest snapshots are dropped.
+pub struct History<T: Copy> {
+    initial: Option<Snapshot<T>>,
+    snapshots: VecDeque<Snapshot<T>>,
+    capacity: usize,
+}
+
+impl<T: Copy + PartialOrd> History<T> {
+    pub fn new(capacity: usize) -> History<T> {
+        History {
+            initial: None,
+            snapshots: VecDeque::new(),
+            capacity,
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.initial = None;
+        self.snapshots.clear();
+    }
+
+    // Generations which can be restored, oldest first
+    pub fn generations(&self) -> Vec<u64> {
+        self.initial.iter().chain(self.snapshots.iter()).map(|s| s.generation).collect()
+    }
+
+    // Should be called right before the grid is stepped
+    pub fn record(&mut self, grid: &dyn Grid<T>) {
+        if self.initial.is_none() {
+            self.initial = Some(Snapshot::take(grid));
+            return;
+        }
+        if self.capacity == 0 || grid.population() > MAX_POPULATION {
+            return;
+        }
+        let snapshot = Snapshot::take(grid);
+        let mut cells: usize = self.snapshots.iter().map(|s| s.cells.len()).sum();
+        while self.snapshots.len() == self.capacity || (cells + snapshot.cells.len() > MAX_CELLS && !self.snapshots.is_empty()) {
+            cells -= self.snapshots.pop_front().unwrap().cells.len();
+        }
+        self.snapshots.push_back(snapshot);
+    }
+
+    // Restores the previous recorded generation
+    pub fn back(&mut self, grid: &mut dyn Grid<T>) -> bool {
+        match self.snapshots.pop_back() {
+            Some(s) => {
+                s.restore(grid);
+                true
+            },
+            None => self.rewind(grid),
+        }
+    }
+
+    pub fn rewind(&mut self, grid: &mut dyn Grid<T>) -> bool {
+        self.snapshots.clear();
+        match &self.initial {
+            Some(s) => {
+                s.restore(grid);
+                true
+            },
+            None => false,
+        }
+    }
+
+    // Restores the latest snapshot not newer than the requested generation and advances
+    // from there
+    pub fn jump(&mut self, grid: &mut dyn Grid<T>, generation: u64) -> Result<(), String> {
+        if generation > grid.generation() {
+            return Err(format!("Generation {} is not computed yet", generation));
+        }
+        while self.snapshots.back().is_some_and(|s| s.generation > generation) {
+            self.snapshots.pop_back();
+        }
+        let snapshot = match self.snapshots.pop_back() {
+            Some(s) => s,
+            None => match &self.initial {
+                Some(s) if s.generation <= generation => Snapshot { generation: s.generation, cells: s.cells.clone() },
+                _ => return Err(format!("Generation {} is not in the history", generation)),
+            },
+        };
+        snapshot.restore(grid);
+        if grid.generation() < generation {
+            self.record(grid);
+            grid.advance(generation - grid.generation());
+        }
+        Ok(())
+    }
+}
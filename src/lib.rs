@@ -0,0 +1,17 @@
+pub mod array;
+pub mod coord;
+pub mod grid;
+pub mod hashlife;
+pub mod history;
+pub mod map;
+pub mod neighborhood;
+pub mod pattern;
+pub mod period;
+pub mod region;
+pub mod rule;
+pub mod shapes;
+pub mod state;
+pub mod stats;
+pub mod table;
+pub mod tiles;
+pub mod topology;
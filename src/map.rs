@@ -1,33 +1,84 @@
 use std::collections::hash_map::{Entry, Keys};
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 
 use crate::coord::Coord;
+use crate::grid::Grid;
+use crate::neighborhood::Neighborhood;
+use crate::rule::Rule;
 use crate::state::State;
+use crate::stats::Stats;
+use crate::topology::{Extent, Topology};
 
 use std::hash::Hash;
-use std::ops::Add;
+use std::ops::{Add, Rem, Sub};
 use std::cmp::{min, max};
 
 use std::ops::AddAssign;
+use std::thread;
 
 pub struct Map<T> {
     map: HashMap<T, HashMap<T, State>>,
+    rule: Rule,
+    generation: u64,
+    population: u64,
+    threads: usize,
 }
 
-impl<T: Eq + Hash + Copy + From<i32> + Add<Output = T> + AddAssign + Ord> Map<T> {
+impl<T: Eq + Hash + Copy + From<i32> + Add<Output = T> + Sub<Output = T> + Rem<Output = T> + AddAssign + Ord + Send + Sync> Default for Map<T> {
+    fn default() -> Map<T> {
+        Map::new()
+    }
+}
+
+impl<T: Eq + Hash + Copy + From<i32> + Add<Output = T> + Sub<Output = T> + Rem<Output = T> + AddAssign + Ord + Send + Sync> Map<T> {
     pub fn new() -> Map<T> {
         Map {
             map: HashMap::new(),
+            rule: Rule::default(),
+            generation: 0,
+            population: 0,
+            threads: 1,
         }
     }
 
+    pub fn rule(&self) -> Rule {
+        self.rule.clone()
+    }
+
+    // Cells which do not fit into a bounded universe are removed
+    pub fn set_rule(&mut self, rule: Rule) {
+        let topology = rule.topology();
+        self.rule = rule;
+        let outside: Vec<Coord<T>> = self.iter().filter(|c| !topology.contains(*c)).collect();
+        for c in outside {
+            self.kill(c);
+        }
+    }
+
+    pub fn generation(&self) -> u64 {
+        self.generation
+    }
+
+    pub fn population(&self) -> u64 {
+        self.population
+    }
+
+    pub fn set_threads(&mut self, threads: usize) {
+        self.threads = max(threads, 1);
+    }
+
     pub fn map(&self) -> &HashMap<T, HashMap<T, State>> {
         &self.map
     }
 
     pub fn set(&mut self, coord: Coord<T>, s: State) {
-        let ymap = self.map.entry(coord.0).or_insert(HashMap::new());
-        ymap.insert(coord.1, s);
+        if !self.rule.topology().contains(coord) {
+            return;
+        }
+        let ymap = self.map.entry(coord.0).or_default();
+        if ymap.insert(coord.1, s).is_none() {
+            self.population += 1;
+        }
     }
 
     pub fn get(&self, coord: Coord<T>) -> Option<State> {
@@ -43,6 +94,7 @@ impl<T: Eq + Hash + Copy + From<i32> + Add<Output = T> + AddAssign + Ord> Map<T>
         if let Entry::Occupied(mut xo) = self.map.entry(coord.0) {
             if let Entry::Occupied(yo) = xo.get_mut().entry(coord.1) {
                 yo.remove();
+                self.population -= 1;
             }
         }
     }
@@ -60,18 +112,287 @@ impl<T: Eq + Hash + Copy + From<i32> + Add<Output = T> + AddAssign + Ord> Map<T>
         }
     }
 
+    // Bitmask of the alive neighbors, see Rule for the bit order
+    pub fn neighborhood(&self, coord: Coord<T>) -> u8 {
+        let topology = self.rule.topology();
+        let mut config = 0;
+        let mut bit = 0;
+        for dy in -1..2 {
+            for dx in -1..2 {
+                if !(dx == 0 && dy == 0) {
+                    if let Some(nc) = topology.wrap(coord.offset(Coord(dx.into(), dy.into()))) {
+                        if self.get(nc) == Some(State::ALIVE) {
+                            config |= 1 << bit;
+                        }
+                    }
+                    bit += 1;
+                }
+            }
+        }
+        config & self.rule.neighborhood().mask()
+    }
+
     pub fn ncount(&self, coord: Coord<T>) -> u8 {
-        let mut n = 0;
-        for dx in -1..2 {
-            for dy in -1..2 {
+        self.neighborhood(coord).count_ones() as u8
+    }
+
+    // States of the neighbors (0 for dead) in the order of the configuration bits
+    pub fn neighbor_states(&self, coord: Coord<T>) -> [u8; 8] {
+        let topology = self.rule.topology();
+        let mut states = [0; 8];
+        let mut i = 0;
+        for dy in -1..2 {
+            for dx in -1..2 {
                 if !(dx == 0 && dy == 0) {
-                    if let Some(_st) = self.get(coord.offset(Coord(dx.into(), dy.into()))) {
-                        n += 1;
+                    if let Some(nc) = topology.wrap(coord.offset(Coord(dx.into(), dy.into()))) {
+                        states[i] = self.get(nc).map_or(0, |s| s.0);
+                    }
+                    i += 1;
+                }
+            }
+        }
+        states
+    }
+
+    // Cells changing their state in the given columns, with the new state (None if dead)
+    fn step_band(&self, columns: &[(T, HashSet<T>)]) -> Vec<(Coord<T>, Option<State>)> {
+        let mut changes = Vec::new();
+        for (x, ys) in columns {
+            for y in ys {
+                let coord = Coord(*x, *y);
+                let state = self.get(coord);
+                let next = match self.rule.table() {
+                    Some(_) => self.rule.next_state_table(state, &self.neighbor_states(coord)),
+                    None => self.rule.next_state(state, self.neighborhood(coord)),
+                };
+                if next != state {
+                    changes.push((coord, next));
+                }
+            }
+        }
+        changes
+    }
+
+    // Larger than Life rules count the neighbors with summed-area tables of the alive cells,
+    // so each count takes a few lookups per row of the neighborhood (a constant four for the
+    // Moore one) regardless of the radius. The plane is cut into square tiles at least as wide
+    // as the radius, and only the tiles next to one holding some cell may change. Each of them
+    // gets its own table covering the tile and the radius around it. Universes with glued
+    // edges are covered whole and the margins are filled by following the edges.
+    fn step_range(&self) -> Vec<(Coord<T>, Option<State>)> {
+        let topology = self.rule.topology();
+        let neighborhood = self.rule.neighborhood();
+        let radius = self.rule.radius();
+        let r = radius as i32;
+        let size = max(16, r);
+        let (zero, one, step): (T, T, T) = (0.into(), 1.into(), size.into());
+        let coords = |from: T, to: T, step: T| {
+            let mut res = Vec::new();
+            let mut v = from;
+            while v <= to {
+                res.push(v);
+                v += step;
+            }
+            res
+        };
+        // Start of the tile containing the coordinate
+        let align = |v: T| {
+            let m = v % step;
+            if m < zero { v - m - step } else { v - m }
+        };
+        // Along the dimensions with glued edges the whole universe is covered, along the others
+        // the tiles next to the occupied ones
+        let glued = match topology {
+            Topology::Infinite | Topology::Plane(..) => (None, None),
+            _ => topology.extents::<T>(),
+        };
+        let occupied: HashSet<(T, T)> = self.iter().map(|c| (align(c.0), align(c.1))).collect();
+        let mut tiles: Vec<Coord<T>> = match glued {
+            (None, None) => {
+                occupied.into_iter().flat_map(|(x, y)| {
+                    (-1..=1).flat_map(move |dx| (-1..=1).map(move |dy| Coord(x + (dx * size).into(), y + (dy * size).into())))
+                }).collect()
+            },
+            _ => {
+                let axis = |extent: Extent<T>, vs: Vec<T>| match extent {
+                    Some((lo, hi)) => coords(align(lo), hi, step),
+                    None => vs.into_iter().flat_map(|v| [v - step, v, v + step]).collect(),
+                };
+                let xs = axis(glued.0, occupied.iter().map(|t| t.0).collect());
+                axis(glued.1, occupied.iter().map(|t| t.1).collect()).into_iter().flat_map(|y| xs.iter().map(move |x| Coord(*x, y))).collect()
+            },
+        };
+        tiles.sort_by_key(|c| (c.0, c.1));
+        tiles.dedup_by_key(|c| (c.0, c.1));
+
+        let tile = |o: &Coord<T>| {
+            let last: T = (size - 1).into();
+            let (xs, ys) = (coords(o.0 - r.into(), o.0 + last + r.into(), one), coords(o.1 - r.into(), o.1 + last + r.into(), one));
+
+            // sums[y][x] holds the number of alive cells above and to the left of (x, y)
+            let width = xs.len() + 1;
+            let mut sums = vec![0u32; width * (ys.len() + 1)];
+            for (j, y) in ys.iter().enumerate() {
+                let mut row = 0;
+                for (i, x) in xs.iter().enumerate() {
+                    if topology.wrap(Coord(*x, *y)).is_some_and(|c| self.get(c) == Some(State::ALIVE)) {
+                        row += 1;
+                    }
+                    sums[(j + 1) * width + i + 1] = sums[j * width + i + 1] + row;
+                }
+            }
+            // Alive cells in the rectangle with both corners inclusive
+            let rect = |x0: usize, y0: usize, x1: usize, y1: usize| {
+                sums[(y1 + 1) * width + x1 + 1] + sums[y0 * width + x0] - sums[y0 * width + x1 + 1] - sums[(y1 + 1) * width + x0]
+            };
+
+            let r = radius as usize;
+            let mut changes = Vec::new();
+            for (j, y) in ys.iter().enumerate().take(ys.len() - r).skip(r) {
+                for (i, x) in xs.iter().enumerate().take(xs.len() - r).skip(r) {
+                    let coord = Coord(*x, *y);
+                    if !topology.contains(coord) {
+                        continue;
+                    }
+                    let count = match neighborhood {
+                        Neighborhood::Moore => rect(i - r, j - r, i + r, j + r),
+                        _ => (j - r..=j + r).map(|y| {
+                            let span = neighborhood.span(y.abs_diff(j) as u32, radius) as usize;
+                            rect(i - span, y, i + span, y)
+                        }).sum(),
+                    };
+                    let state = self.get(coord);
+                    if count == 0 && state.is_none() {
+                        continue;
+                    }
+                    let next = self.rule.next_state_count(state, count);
+                    if next != state {
+                        changes.push((coord, next));
+                    }
+                }
+            }
+            changes
+        };
+
+        if self.threads > 1 && tiles.len() > 1 {
+            let size = tiles.len().div_ceil(self.threads);
+            let tile = &tile;
+            thread::scope(|s| {
+                let handles: Vec<_> = tiles.chunks(size).map(|b| s.spawn(move || b.iter().flat_map(tile).collect::<Vec<_>>())).collect();
+                handles.into_iter().flat_map(|h| h.join().unwrap()).collect()
+            })
+        } else {
+            tiles.iter().flat_map(tile).collect()
+        }
+    }
+
+    // Block rules only need to look at the blocks containing some alive cells
+    fn step_blocks(&self) -> Vec<(Coord<T>, Option<State>)> {
+        let topology = self.rule.topology();
+        let odd = self.generation % 2 == 1;
+        let blocks = self.rule.blocks(odd).unwrap();
+        let (zero, one, two): (T, T, T) = (0.into(), 1.into(), 2.into());
+        let shift = if odd { one } else { zero };
+        // Start of the block containing the coordinate
+        let align = |v: T| {
+            let r = (v - shift) % two;
+            if r < zero { v - r - two } else { v - r }
+        };
+        // Blocks across glued edges are reached from both sides, so they are told apart by
+        // their cells rather than by their origins
+        let mut squares: Vec<[Option<Coord<T>>; 4]> = self.iter().map(|c| {
+            let o = Coord(align(c.0), align(c.1));
+            [o, Coord(o.0 + one, o.1), Coord(o.0, o.1 + one), Coord(o.0 + one, o.1 + one)].map(|c| topology.wrap(c))
+        }).collect();
+        let key = |cells: &[Option<Coord<T>>; 4]| {
+            let mut key = cells.map(|c| c.map(|c| (c.0, c.1)));
+            key.sort();
+            key
+        };
+        squares.sort_by_key(key);
+        squares.dedup_by_key(|cells| key(cells));
+
+        let mut changes = Vec::new();
+        for cells in squares {
+            let block = cells.iter().enumerate()
+                .filter(|(_, c)| c.is_some_and(|c| self.get(c) == Some(State::ALIVE)))
+                .fold(0u8, |b, (i, _)| b | 1 << i);
+            let next = blocks[block as usize];
+            for (i, c) in cells.iter().enumerate() {
+                match c {
+                    Some(c) if (block ^ next) & (1 << i) != 0 => changes.push((*c, (next & (1 << i) != 0).then_some(State::ALIVE))),
+                    _ => (),
+                }
+            }
+        }
+        changes
+    }
+
+    // Columns are split into contiguous bands, one per thread. Changes are only applied
+    // after all the bands are computed, so the result does not depend on the thread count.
+    pub fn step(&mut self) -> Stats {
+        if self.rule.range().is_some() {
+            let changes = self.step_range();
+            return self.apply(vec![changes]);
+        }
+        if self.rule.blocks(false).is_some() {
+            let changes = self.step_blocks();
+            return self.apply(vec![changes]);
+        }
+        let topology = self.rule.topology();
+        let mut candidates: HashMap<T, HashSet<T>> = HashMap::new();
+        for i in self.iter() {
+            // Refractory cells of Generations rules only change by themselves, while all the
+            // states of a rule table affect the neighbors
+            if self.get(i) != Some(State::ALIVE) && self.rule.table().is_none() {
+                candidates.entry(i.0).or_default().insert(i.1);
+                continue;
+            }
+            for dx in -1..=1 {
+                for dy in -1..=1 {
+                    if let Some(c) = topology.wrap(i.offset(Coord(dx.into(), dy.into()))) {
+                        candidates.entry(c.0).or_default().insert(c.1);
                     }
                 }
             }
         }
-        n
+        let mut columns: Vec<(T, HashSet<T>)> = candidates.into_iter().collect();
+        columns.sort_by_key(|(x, _)| *x);
+
+        let changes = if self.threads > 1 && columns.len() > 1 {
+            let band = columns.len().div_ceil(self.threads);
+            let this = &*self;
+            thread::scope(|s| {
+                let handles: Vec<_> = columns.chunks(band).map(|b| s.spawn(move || this.step_band(b))).collect();
+                handles.into_iter().map(|h| h.join().unwrap()).collect::<Vec<_>>()
+            })
+        } else {
+            vec![self.step_band(&columns)]
+        };
+        self.apply(changes)
+    }
+
+    fn apply(&mut self, changes: Vec<Vec<(Coord<T>, Option<State>)>>) -> Stats {
+        let mut stats = Stats::default();
+        for (c, next) in changes.into_iter().flatten() {
+            if self.get(c) == Some(State::ALIVE) {
+                stats.deaths += 1;
+            } else if next == Some(State::ALIVE) {
+                stats.births += 1;
+            }
+            match next {
+                Some(s) => self.set(c, s),
+                None => self.kill(c),
+            }
+        }
+
+        self.generation += 1;
+        if self.generation.is_multiple_of(10) {
+            self.gc();
+        }
+
+        stats.population = self.population;
+        stats
     }
 
     pub fn dims(&self) -> (Coord<T>, Coord<T>) {
@@ -81,10 +402,10 @@ impl<T: Eq + Hash + Copy + From<i32> + Add<Output = T> + AddAssign + Ord> Map<T>
         let mut maxy: Option<T> = None;
 
         for i in self.iter() {
-            minx = Some(if minx.is_none() { i.0 } else { min(i.0, minx.unwrap()) });
-            miny = Some(if miny.is_none() { i.1 } else { min(i.1, miny.unwrap()) });
-            maxx = Some(if maxx.is_none() { i.0 } else { max(i.0, maxx.unwrap()) });
-            maxy = Some(if maxy.is_none() { i.1 } else { max(i.1, maxy.unwrap()) });
+            minx = Some(minx.map_or(i.0, |v| min(i.0, v)));
+            miny = Some(miny.map_or(i.1, |v| min(i.1, v)));
+            maxx = Some(maxx.map_or(i.0, |v| max(i.0, v)));
+            maxy = Some(maxy.map_or(i.1, |v| max(i.1, v)));
         }
 
         (Coord(minx.unwrap(), miny.unwrap()), Coord(maxx.unwrap(), maxy.unwrap()))
@@ -99,7 +420,7 @@ impl<T: Eq + Hash + Copy + From<i32> + Add<Output = T> + AddAssign + Ord> Map<T>
             x = 0.into();
             for ch in row.to_string().chars() {
                 if ch != ' ' {
-                    newmap.set(Coord(x, y), State::Alive);
+                    newmap.set(Coord(x, y), State::ALIVE);
                 }
                 x += 1.into();
             }
@@ -109,7 +430,7 @@ impl<T: Eq + Hash + Copy + From<i32> + Add<Output = T> + AddAssign + Ord> Map<T>
         newmap
     }
 
-    pub fn iter(&self) -> MapIter<T> {
+    pub fn iter(&self) -> MapIter<'_, T> {
         MapIter {
             map: self,
             xkeys: None,
@@ -120,6 +441,62 @@ impl<T: Eq + Hash + Copy + From<i32> + Add<Output = T> + AddAssign + Ord> Map<T>
     }
 }
 
+impl<T: Eq + Hash + Copy + From<i32> + Add<Output = T> + Sub<Output = T> + Rem<Output = T> + AddAssign + Ord + Send + Sync> Grid<T> for Map<T> {
+    fn get(&self, coord: Coord<T>) -> Option<State> {
+        Map::get(self, coord)
+    }
+
+    fn set(&mut self, coord: Coord<T>, s: State) {
+        Map::set(self, coord, s)
+    }
+
+    fn kill(&mut self, coord: Coord<T>) {
+        Map::kill(self, coord)
+    }
+
+    fn iter(&self) -> Box<dyn Iterator<Item = Coord<T>> + '_> {
+        Box::new(Map::iter(self))
+    }
+
+    fn clear(&mut self) {
+        self.map.clear();
+        self.population = 0;
+    }
+
+    fn dims(&self) -> (Coord<T>, Coord<T>) {
+        Map::dims(self)
+    }
+
+    fn rule(&self) -> Rule {
+        self.rule.clone()
+    }
+
+    fn set_rule(&mut self, rule: Rule) -> Result<(), String> {
+        Map::set_rule(self, rule);
+        Ok(())
+    }
+
+    fn generation(&self) -> u64 {
+        self.generation
+    }
+
+    fn set_generation(&mut self, generation: u64) {
+        self.generation = generation;
+    }
+
+    fn population(&self) -> u64 {
+        self.population
+    }
+
+    fn step(&mut self) -> Stats {
+        Map::step(self)
+    }
+
+    fn set_threads(&mut self, threads: usize) {
+        Map::set_threads(self, threads)
+    }
+}
+
 pub struct MapIter<'a, T> {
     map: &'a Map<T>,
     xkeys: Option<Keys<'a, T, HashMap<T, State>>>,
@@ -128,13 +505,13 @@ pub struct MapIter<'a, T> {
     ykey: Option<&'a T>,
 }
 
-impl<'a, T: Eq + Hash> MapIter<'_, T> {
+impl<T: Eq + Hash> MapIter<'_, T> {
     fn next_xkey(&mut self) -> bool {
         match &mut self.xkeys {
             Some(keys) => {
                 self.xkey = keys.next();
-                if self.xkey.is_some() {
-                    if let Some(yval) = self.map.map.get(self.xkey.unwrap()) {
+                if let Some(xkey) = self.xkey {
+                    if let Some(yval) = self.map.map.get(xkey) {
                         self.ykeys = Some(yval.keys());
                         self.ykey = None;
                     } else {
@@ -0,0 +1,47 @@
+// Cells counted as neighbors, selected with a Golly-style suffix of the rule ("B2/S34H").
+// The hexagonal grid is emulated on the square one by leaving out the NE and SW neighbors,
+// so that each row is shifted by half a cell against the next one. The circular neighborhood
+// only exists in Larger than Life rules, where it covers the cells whose centers lie within
+// the radius plus one half; at range 1 it is the same as Moore.
+#[derive(Copy, Clone, PartialEq)]
+pub enum Neighborhood {
+    Moore,
+    VonNeumann,
+    Hexagonal,
+    Circular,
+}
+
+impl Neighborhood {
+    // Neighbors which are part of the neighborhood, as a bitmask of the 3x3 configuration
+    // (see Rule for the bit order)
+    pub fn mask(&self) -> u8 {
+        match self {
+            Neighborhood::Moore | Neighborhood::Circular => 0xff,
+            Neighborhood::VonNeumann => 2 | 8 | 16 | 64,
+            Neighborhood::Hexagonal => !(4 | 32),
+        }
+    }
+
+    pub fn size(&self) -> u8 {
+        self.mask().count_ones() as u8
+    }
+
+    pub fn suffix(&self) -> &str {
+        match self {
+            Neighborhood::Moore | Neighborhood::Circular => "",
+            Neighborhood::VonNeumann => "V",
+            Neighborhood::Hexagonal => "H",
+        }
+    }
+
+    // Cells to each side of the middle column covered in the row dy above or below the
+    // middle one, for the neighborhoods extended to the radius
+    pub fn span(&self, dy: u32, radius: u32) -> u32 {
+        match self {
+            Neighborhood::Moore => radius,
+            Neighborhood::VonNeumann => radius - dy,
+            Neighborhood::Circular => (radius * radius + radius - dy * dy).isqrt(),
+            Neighborhood::Hexagonal => unreachable!("Hexagonal neighborhood has no extended range"),
+        }
+    }
+}
@@ -0,0 +1,436 @@
+use std::io::{self, BufReader, BufRead};
+use std::path::Path;
+use std::fs::{self, File};
+use std::fmt;
+use std::ops::{Add, Sub};
+
+use crate::coord::Coord;
+use crate::grid::Grid;
+use crate::rule::Rule;
+use crate::state::State;
+
+// Pattern as read from a file: the cells relative to the upper left corner of the pattern and
+// the properties given by the comment, header and position lines. The writers only take the
+// descriptive fields from it, the cells come from the grid being saved.
+#[derive(Clone, Default)]
+pub struct Pattern {
+    pub cells: Vec<(Coord<i64>, State)>,
+    pub name: Option<String>,
+    pub author: Option<String>,
+    pub comments: Vec<String>,
+    pub url: Option<String>,
+    pub width: Option<i64>,
+    pub height: Option<i64>,
+    pub rule: Option<Rule>,
+    // Position of the upper left corner of the bounding box
+    pub offset: Option<Coord<i64>>,
+}
+
+impl Pattern {
+    // Any character other than a space in the rows is an alive cell
+    pub fn new<S: AsRef<str>>(rows: &[S]) -> Pattern {
+        let mut cells = Vec::new();
+        for (y, row) in rows.iter().enumerate() {
+            for (x, ch) in row.as_ref().chars().enumerate() {
+                if ch != ' ' {
+                    cells.push((Coord(x as i64, y as i64), State::ALIVE));
+                }
+            }
+        }
+        Pattern { cells, ..Pattern::default() }
+    }
+
+    // Size of the bounding box of the cells, counted from the upper left corner of the pattern
+    pub fn size(&self) -> (i64, i64) {
+        let width = self.cells.iter().map(|(c, _)| c.0 + 1).max().unwrap_or(0);
+        let height = self.cells.iter().map(|(c, _)| c.1 + 1).max().unwrap_or(0);
+        (width, height)
+    }
+
+    // The first comment looking like a link is taken as the source URL
+    fn add_comment(&mut self, comment: &str) {
+        let comment = comment.trim();
+        let is_url = ["http://", "https://", "www."].iter().any(|p| comment.starts_with(p));
+        if is_url && self.url.is_none() {
+            self.url = Some(comment.to_string());
+        } else {
+            self.comments.push(comment.to_string());
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum ErrorKind {
+    Io(io::Error),
+    // Run count which is zero or too large
+    BadCount(String),
+    UnexpectedChar(char),
+    // The RLE body is not terminated with '!'
+    MissingEnd,
+    // Multi-state cell letters beyond the 255 states RLE can describe
+    UnsupportedState(String),
+    // Malformed header, rule or position line, or a body larger than declared
+    InvalidHeader(String),
+}
+
+// Error reading a pattern file. Line and column numbers start from 1, zero means the
+// error is not bound to a particular place, like a missing file.
+#[derive(Debug)]
+pub struct ParseError {
+    pub kind: ErrorKind,
+    pub line: usize,
+    pub column: usize,
+}
+
+impl ParseError {
+    fn new(kind: ErrorKind, line: usize, column: usize) -> ParseError {
+        ParseError { kind, line, column }
+    }
+}
+
+impl From<io::Error> for ParseError {
+    fn from(e: io::Error) -> ParseError {
+        ParseError::new(ErrorKind::Io(e), 0, 0)
+    }
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.line > 0 {
+            write!(f, "Line {}, column {}: ", self.line, self.column)?;
+        }
+        match &self.kind {
+            ErrorKind::Io(e) => write!(f, "{}", e),
+            ErrorKind::BadCount(n) => write!(f, "Invalid run count '{}'", n),
+            ErrorKind::UnexpectedChar(c) => write!(f, "Unexpected character '{}'", c),
+            ErrorKind::MissingEnd => write!(f, "Pattern is not terminated with '!'"),
+            ErrorKind::UnsupportedState(c) => write!(f, "Cell state '{}' is not supported", c),
+            ErrorKind::InvalidHeader(msg) => write!(f, "{}", msg),
+        }
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+fn invalid(msg: String) -> ErrorKind {
+    ErrorKind::InvalidHeader(msg)
+}
+
+// LifeHistory is Life with extra states marking the cells which have ever been alive
+// and the like. Odd states are alive, even ones are dead.
+fn is_life_history(rule: &str) -> bool {
+    rule.split(':').next().unwrap().trim().eq_ignore_ascii_case("LifeHistory")
+}
+
+// Parses "x = 36, y = 9, rule = B3/S23". The rule goes last as it may contain commas itself.
+// Returns whether the pattern uses the LifeHistory states.
+fn parse_header(line: &str, pattern: &mut Pattern, dir: Option<&Path>) -> Result<bool, ErrorKind> {
+    let (dims, rule) = match line.find("rule") {
+        Some(i) => (&line[..i], Some(&line[i..])),
+        None => (line, None),
+    };
+    for part in dims.split(',').map(|p| p.trim()).filter(|p| !p.is_empty()) {
+        let (key, value) = part.split_once('=').ok_or(invalid(format!("Invalid header entry '{}'", part)))?;
+        let value: i64 = value.trim().parse().map_err(|_| invalid(format!("Invalid pattern size '{}'", value.trim())))?;
+        match key.trim() {
+            "x" => pattern.width = Some(value),
+            "y" => pattern.height = Some(value),
+            k => return Err(invalid(format!("Unknown header entry '{}'", k))),
+        }
+    }
+    if let Some(rule) = rule {
+        let (_, value) = rule.split_once('=').ok_or(invalid(format!("Invalid header entry '{}'", rule)))?;
+        let value = value.trim();
+        if is_life_history(value) {
+            let mut rule = Rule::conway();
+            if let Some((_, topology)) = value.split_once(':') {
+                rule.set_topology(topology.parse().map_err(invalid)?);
+            }
+            pattern.rule = Some(rule);
+            return Ok(true);
+        }
+        pattern.rule = Some(Rule::parse_in(value, dir).map_err(invalid)?);
+    }
+    Ok(false)
+}
+
+// Positions and sizes are kept well within the coordinates the backends can handle
+const MAX_POSITION: i64 = 1 << 40;
+
+// Parses the "#P x y" and "#R x y" lines
+fn parse_position(line: &str) -> Result<Coord<i64>, ErrorKind> {
+    let mut values = line[2..].split_whitespace().map(|v| v.parse::<i64>());
+    match (values.next(), values.next()) {
+        (Some(Ok(x)), Some(Ok(y))) if x.unsigned_abs() > MAX_POSITION as u64 || y.unsigned_abs() > MAX_POSITION as u64 => {
+            Err(invalid(format!("Position {} {} is too far from the origin", x, y)))
+        },
+        (Some(Ok(x)), Some(Ok(y))) => Ok(Coord(x, y)),
+        _ => Err(invalid(format!("Invalid position line '{}'", line))),
+    }
+}
+
+// The body may be smaller than the declared size, as trailing dead cells are not written
+fn validate(pattern: &Pattern) -> Result<(), ErrorKind> {
+    let (width, height) = pattern.size();
+    if width > MAX_POSITION || height > MAX_POSITION {
+        return Err(invalid(format!("Pattern is {}x{}, too large", width, height)));
+    }
+    if pattern.width.is_some_and(|w| width > w) || pattern.height.is_some_and(|h| height > h) {
+        return Err(invalid(format!(
+            "Pattern is {}x{}, larger than the declared {}x{}",
+            width, height, pattern.width.unwrap_or(width), pattern.height.unwrap_or(height),
+        )));
+    }
+    Ok(())
+}
+
+fn read_lines<P: AsRef<Path>>(file: P) -> io::Result<Vec<String>> {
+    BufReader::new(File::open(file)?).lines().collect()
+}
+
+// Run count preceding a tag, one if omitted
+fn run_length(numstr: &str) -> Result<usize, ErrorKind> {
+    if numstr.is_empty() {
+        return Ok(1);
+    }
+    match numstr.parse::<i32>() {
+        Ok(n) if n > 0 => Ok(n as usize),
+        _ => Err(ErrorKind::BadCount(numstr.to_string())),
+    }
+}
+
+// Two-state patterns use 'b' for dead and 'o' for alive cells. Multi-state ones use '.' for
+// dead cells and 'A' to 'X' for the states 1 to 24, the higher states are prefixed with a
+// letter from 'p' to 'y' ("pA" is 25, "yO" is 255).
+pub fn read_rle<P: AsRef<Path>>(file: P) -> Result<Pattern, ParseError> {
+    let rle = read_lines(&file)?;
+    let mut pattern = Pattern::default();
+    let mut history = false;
+    let mut numstr = String::new();
+    let mut prefix: Option<char> = None;
+    let (mut x, mut y) = (0, 0);
+    for (i, l) in rle.iter().enumerate() {
+        let line = i + 1;
+        if l.starts_with("#P") || l.starts_with("#R") {
+            pattern.offset = Some(parse_position(l).map_err(|k| ParseError::new(k, line, 1))?);
+        } else if let Some(name) = l.strip_prefix("#N") {
+            pattern.name = Some(name.trim().to_string());
+        } else if let Some(author) = l.strip_prefix("#O") {
+            pattern.author = Some(author.trim().to_string());
+        } else if let Some(comment) = l.strip_prefix("#C").or(l.strip_prefix("#c")) {
+            pattern.add_comment(comment);
+        } else if l.starts_with("#") {
+            // println!("Ignored comment {}", l);
+        } else if l.starts_with("x ") || l.starts_with("x=") {
+            history = parse_header(l, &mut pattern, file.as_ref().parent()).map_err(|k| ParseError::new(k, line, 1))?;
+        } else {
+            for (j, c) in l.chars().enumerate() {
+                let err = |k| ParseError::new(k, line, j + 1);
+                if prefix.is_some() && !c.is_ascii_uppercase() {
+                    return Err(err(ErrorKind::UnexpectedChar(c)));
+                }
+                match c {
+                    '0' ..= '9' => {
+                        numstr.push(c);
+                    },
+                    'b' | '.' => {
+                        x += run_length(&numstr).map_err(err)? as i64;
+                        numstr = String::new();
+                    },
+                    'o' | 'A' ..= 'Z' => {
+                        let p = prefix.take();
+                        let state = match (p, c) {
+                            (None, 'o') => Some(1),
+                            (None, 'A' ..= 'X') => Some(c as u32 - 'A' as u32 + 1),
+                            (Some(p), 'A' ..= 'X') => Some((p as u32 - 'p' as u32 + 1) * 24 + c as u32 - 'A' as u32 + 1),
+                            _ => None,
+                        };
+                        let state = match state.and_then(|s| u8::try_from(s).ok()) {
+                            Some(s) => State(s),
+                            None => return Err(err(ErrorKind::UnsupportedState(p.into_iter().chain([c]).collect()))),
+                        };
+                        for _ in 0..run_length(&numstr).map_err(err)? {
+                            pattern.cells.push((Coord(x, y), state));
+                            x += 1;
+                        }
+                        numstr = String::new();
+                    },
+                    'p' ..= 'y' => prefix = Some(c),
+                    '$' => {
+                        y += run_length(&numstr).map_err(err)? as i64;
+                        x = 0;
+                        numstr = String::new();
+                    },
+                    '!' => {
+                        if !numstr.is_empty() {
+                            return Err(err(ErrorKind::BadCount(numstr)));
+                        }
+                        if history {
+                            pattern.cells.retain(|(_, s)| s.0 % 2 == 1);
+                            pattern.cells.iter_mut().for_each(|(_, s)| *s = State::ALIVE);
+                        }
+                        validate(&pattern).map_err(err)?;
+                        return Ok(pattern);
+                    }
+                    c if c.is_whitespace() => (),
+                    _ => return Err(err(ErrorKind::UnexpectedChar(c))),
+                }
+            }
+        }
+    }
+    let column = rle.last().map_or(0, |l| l.chars().count()) + 1;
+    Err(ParseError::new(ErrorKind::MissingEnd, rle.len(), column))
+}
+
+// Dead cells are '.', alive ones are 'O' ('*' is accepted as well, as used by older files)
+pub fn read_cells<P: AsRef<Path>>(file: P) -> Result<Pattern, ParseError> {
+    let cells = read_lines(file)?;
+    let mut pattern = Pattern::default();
+    let mut y = 0;
+
+    for (i, l) in cells.iter().enumerate() {
+        if let Some(name) = l.strip_prefix("!Name:") {
+            pattern.name = Some(name.trim().to_string());
+        } else if let Some(author) = l.strip_prefix("!Author:") {
+            pattern.author = Some(author.trim().to_string());
+        } else if let Some(comment) = l.strip_prefix("!") {
+            pattern.add_comment(comment);
+        } else {
+            for (j, c) in l.trim_end().chars().enumerate() {
+                match c {
+                    '.' | ' ' => (),
+                    'O' | '*' => pattern.cells.push((Coord(j as i64, y), State::ALIVE)),
+                    _ => return Err(ParseError::new(ErrorKind::UnexpectedChar(c), i + 1, j + 1)),
+                }
+            }
+            y += 1;
+        }
+    }
+    Ok(pattern)
+}
+
+// Appends a run to the pattern body, starting a new line if it would not fit into 70 columns
+fn push_run(lines: &mut Vec<String>, count: String, tag: &str) {
+    let run = format!("{}{}", count, tag);
+    match lines.last_mut() {
+        Some(line) if line.len() + run.len() <= 70 => *line += &run,
+        _ => lines.push(run),
+    }
+}
+
+// Letters of a state in multi-state RLE, the reverse of what read_rle does
+fn state_letters(s: State) -> String {
+    let n = s.0 as u32 - 1;
+    let letter = char::from_u32('A' as u32 + n % 24).unwrap();
+    match n / 24 {
+        0 => letter.to_string(),
+        p => format!("{}{}", char::from_u32('p' as u32 + p - 1).unwrap(), letter),
+    }
+}
+
+// Runs of one are written without the count
+fn run_count<T: PartialEq + From<i32> + fmt::Display>(n: T) -> String {
+    if n == 1.into() { String::new() } else { n.to_string() }
+}
+
+pub fn encode_rle<T>(grid: &dyn Grid<T>, pattern: &Pattern) -> String where
+    T: Copy + Ord + From<i32> + Add<Output = T> + Sub<Output = T> + fmt::Display,
+{
+    let mut res = String::new();
+    if let Some(name) = &pattern.name {
+        res += &format!("#N {}\n", name);
+    }
+    if let Some(author) = &pattern.author {
+        res += &format!("#O {}\n", author);
+    }
+    for c in pattern.comments.iter().chain(pattern.url.iter()) {
+        res += &format!("#C {}\n", c);
+    }
+
+    let mut cells: Vec<(Coord<T>, State)> = grid.iter().map(|c| (c, grid.get(c).unwrap())).collect();
+    if cells.is_empty() {
+        res += &format!("x = 0, y = 0, rule = {}\n!\n", grid.rule());
+        return res;
+    }
+    cells.sort_by_key(|(c, _)| (c.1, c.0));
+    let multi = cells.iter().any(|(_, s)| *s != State::ALIVE);
+    let tag = |s: State| if multi { state_letters(s) } else { String::from("o") };
+    let (ul, lr) = grid.dims();
+    let one: T = 1.into();
+    res += &format!("x = {}, y = {}, rule = {}\n", lr.0 - ul.0 + one, lr.1 - ul.1 + one, grid.rule());
+
+    let mut lines: Vec<String> = Vec::new();
+    let (mut x, mut y) = (ul.0, ul.1);
+    let mut i = 0;
+    while i < cells.len() {
+        let (c, s) = cells[i];
+        if c.1 > y {
+            push_run(&mut lines, run_count(c.1 - y), "$");
+            (x, y) = (ul.0, c.1);
+        }
+        if c.0 > x {
+            push_run(&mut lines, run_count(c.0 - x), if multi { "." } else { "b" });
+        }
+        let mut n = 1;
+        while i + n < cells.len() && cells[i + n].0.1 == c.1 && cells[i + n].0.0 == c.0 + (n as i32).into() && cells[i + n].1 == s {
+            n += 1;
+        }
+        push_run(&mut lines, run_count(n as i32), &tag(s));
+        x = c.0 + (n as i32).into();
+        i += n;
+    }
+    push_run(&mut lines, String::new(), "!");
+
+    for l in lines {
+        res += &l;
+        res += "\n";
+    }
+    res
+}
+
+pub fn write_rle<P, T>(file: P, grid: &dyn Grid<T>, pattern: &Pattern) -> io::Result<()> where
+    P: AsRef<Path>,
+    T: Copy + Ord + From<i32> + Add<Output = T> + Sub<Output = T> + fmt::Display,
+{
+    fs::write(file, encode_rle(grid, pattern))
+}
+
+// Plaintext rows cropped to the bounding box, with '.' for dead and 'O' for alive cells
+pub fn encode_cells<T>(grid: &dyn Grid<T>, pattern: &Pattern) -> String where
+    T: Copy + Ord + From<i32> + Add<Output = T>,
+{
+    let mut res = String::new();
+    if let Some(name) = &pattern.name {
+        res += &format!("!Name: {}\n", name);
+    }
+    if let Some(author) = &pattern.author {
+        res += &format!("!Author: {}\n", author);
+    }
+    for c in pattern.comments.iter().chain(pattern.url.iter()) {
+        res += &format!("!{}\n", c);
+    }
+    if grid.population() == 0 {
+        return res;
+    }
+
+    let (ul, lr) = grid.dims();
+    let one: T = 1.into();
+    let mut y = ul.1;
+    while y <= lr.1 {
+        let mut x = ul.0;
+        while x <= lr.0 {
+            res.push(if grid.get(Coord(x, y)).is_some() { 'O' } else { '.' });
+            x = x + one;
+        }
+        res.push('\n');
+        y = y + one;
+    }
+    res
+}
+
+pub fn write_cells<P, T>(file: P, grid: &dyn Grid<T>, pattern: &Pattern) -> io::Result<()> where
+    P: AsRef<Path>,
+    T: Copy + Ord + From<i32> + Add<Output = T>,
+{
+    fs::write(file, encode_cells(grid, pattern))
+}
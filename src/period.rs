@@ -0,0 +1,119 @@
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::ops::{Rem, Sub};
+
+use crate::coord::Coord;
+use crate::grid::Grid;
+
+#[derive(Copy, Clone, PartialEq)]
+pub enum Period<T> {
+    StillLife,
+    Oscillator(u64),
+    // Period and the displacement of the pattern over it
+    Spaceship(u64, T, T),
+    // When generations were skipped between the checks, only that the pattern repeats after
+    // that many generations is known, in place or moved by the displacement
+    Divisor(u64),
+    SpaceshipDivisor(u64, T, T),
+}
+
+impl<T: fmt::Display> fmt::Display for Period<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Period::StillLife => write!(f, "still life"),
+            Period::Oscillator(p) => write!(f, "oscillator period {}", p),
+            Period::Spaceship(p, dx, dy) => write!(f, "spaceship period {}, displacement ({},{})", p, dx, dy),
+            Period::Divisor(p) => write!(f, "period divides {}", p),
+            Period::SpaceshipDivisor(p, dx, dy) => write!(f, "spaceship period divides {}, displacement ({},{}) over {}", p, dx, dy, p),
+        }
+    }
+}
+
+// Larger patterns are not checked, hashing them each generation would slow the run down
+const MAX_POPULATION: u64 = 100_000;
+// Hashes kept at most, the older ones are forgotten when reached
+const MAX_GENERATIONS: usize = 10_000;
+
+// Notices when a generation repeats an earlier one, possibly moved. Each generation is
+// hashed with the cells taken relative to the upper left corner of the bounding box, and
+// the hash is remembered with the generation and the corner. Block rules alternate the
+// alignment of the blocks, so only generations of the same parity are compared, and only
+// with the pattern moved by an even number of cells.
+pub struct PeriodDetector<T: Copy> {
+    seen: HashMap<u64, (u64, Coord<T>)>,
+    period: Option<Period<T>>,
+    // Last generation checked and whether some generations were not checked since the start
+    last: Option<u64>,
+    skipped: bool,
+}
+
+impl<T: Copy + Hash + Ord + From<i32> + Sub<Output = T> + Rem<Output = T>> Default for PeriodDetector<T> {
+    fn default() -> PeriodDetector<T> {
+        PeriodDetector::new()
+    }
+}
+
+impl<T: Copy + Hash + Ord + From<i32> + Sub<Output = T> + Rem<Output = T>> PeriodDetector<T> {
+    pub fn new() -> PeriodDetector<T> {
+        PeriodDetector {
+            seen: HashMap::new(),
+            period: None,
+            last: None,
+            skipped: false,
+        }
+    }
+
+    // Should be called whenever the grid changes other than by stepping forward
+    pub fn clear(&mut self) {
+        self.seen.clear();
+        self.period = None;
+        self.last = None;
+        self.skipped = false;
+    }
+
+    pub fn period(&self) -> Option<Period<T>> {
+        self.period
+    }
+
+    // Should be called after each step, returns the period once the pattern repeats. Steps of
+    // more than one generation, by Hashlife or by advancing, only give a multiple of the period.
+    pub fn check(&mut self, grid: &dyn Grid<T>) -> Option<Period<T>> {
+        if grid.population() == 0 || grid.population() > MAX_POPULATION {
+            self.clear();
+            return None;
+        }
+        let (ul, _) = grid.dims();
+        let mut cells: Vec<(T, T, u8)> = grid.iter().map(|c| (c.0 - ul.0, c.1 - ul.1, grid.get(c).unwrap().0)).collect();
+        cells.sort();
+        let mut hasher = DefaultHasher::new();
+        cells.hash(&mut hasher);
+        let generation = grid.generation();
+        self.skipped |= self.last.is_some_and(|g| generation != g + 1);
+        self.last = Some(generation);
+        if grid.rule().blocks(false).is_some() {
+            let (zero, two): (T, T) = (0.into(), 2.into());
+            let parity = |v: T| if v % two < zero { zero - v % two } else { v % two };
+            (generation % 2, parity(ul.0), parity(ul.1)).hash(&mut hasher);
+        }
+        if self.seen.len() >= MAX_GENERATIONS {
+            self.seen.clear();
+        }
+
+        // The entry is moved to the latest occurrence, so that the following generations
+        // are compared against the previous period and not the first one
+        if let Some((g, corner)) = self.seen.insert(hasher.finish(), (generation, ul)).filter(|(g, _)| *g < generation) {
+            let (p, dx, dy) = (generation - g, ul.0 - corner.0, ul.1 - corner.1);
+            let zero: T = 0.into();
+            self.period = Some(match (p, dx == zero && dy == zero) {
+                (_, true) if self.skipped => Period::Divisor(p),
+                _ if self.skipped => Period::SpaceshipDivisor(p, dx, dy),
+                (1, true) => Period::StillLife,
+                (_, true) => Period::Oscillator(p),
+                _ => Period::Spaceship(p, dx, dy),
+            });
+        }
+        self.period
+    }
+}
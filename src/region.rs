@@ -0,0 +1,123 @@
+use std::cmp::{min, max};
+
+use crate::coord::Coord;
+use crate::grid::Grid;
+use crate::state::State;
+
+// How pasted cells are combined with the cells already on the board
+#[derive(Copy, Clone, PartialEq)]
+pub enum PasteMode {
+    // Alive cells are added, nothing is removed
+    Or,
+    // Alive cells toggle the cells under them
+    Xor,
+    // The whole rectangle is replaced, including the dead cells
+    Overwrite,
+}
+
+impl PasteMode {
+    pub fn next(&self) -> PasteMode {
+        match self {
+            PasteMode::Or => PasteMode::Xor,
+            PasteMode::Xor => PasteMode::Overwrite,
+            PasteMode::Overwrite => PasteMode::Or,
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        match self {
+            PasteMode::Or => "or",
+            PasteMode::Xor => "xor",
+            PasteMode::Overwrite => "overwrite",
+        }
+    }
+}
+
+// Normalizes two opposite corners of a rectangle into the upper left and lower right ones
+fn corners(a: Coord<i64>, b: Coord<i64>) -> (Coord<i64>, Coord<i64>) {
+    (Coord(min(a.0, b.0), min(a.1, b.1)), Coord(max(a.0, b.0), max(a.1, b.1)))
+}
+
+// Rectangular piece of a board, with cell coordinates relative to its upper left corner
+#[derive(Clone)]
+pub struct Region {
+    width: i64,
+    height: i64,
+    cells: Vec<(Coord<i64>, State)>,
+}
+
+impl Region {
+    // Copies the rectangle with the given opposite corners, both inclusive
+    pub fn copy(grid: &dyn Grid<i64>, a: Coord<i64>, b: Coord<i64>) -> Region {
+        let (ul, lr) = corners(a, b);
+        Region {
+            width: lr.0 - ul.0 + 1,
+            height: lr.1 - ul.1 + 1,
+            cells: grid.cells_in(ul, lr).into_iter()
+                .map(|c| (Coord(c.0 - ul.0, c.1 - ul.1), grid.get(c).unwrap()))
+                .collect(),
+        }
+    }
+
+    pub fn cut(grid: &mut dyn Grid<i64>, a: Coord<i64>, b: Coord<i64>) -> Region {
+        let region = Region::copy(grid, a, b);
+        let (ul, _) = corners(a, b);
+        for (c, _) in &region.cells {
+            grid.kill(c.offset(ul));
+        }
+        region
+    }
+
+    pub fn width(&self) -> i64 {
+        self.width
+    }
+
+    pub fn height(&self) -> i64 {
+        self.height
+    }
+
+    pub fn cells(&self) -> &[(Coord<i64>, State)] {
+        &self.cells
+    }
+
+    // Rotates by 90 degrees clockwise
+    pub fn rotate(&mut self) {
+        let height = self.height;
+        for (c, _) in self.cells.iter_mut() {
+            *c = Coord(height - 1 - c.1, c.0);
+        }
+        (self.width, self.height) = (self.height, self.width);
+    }
+
+    // Mirrors left to right
+    pub fn flip_h(&mut self) {
+        for (c, _) in self.cells.iter_mut() {
+            c.0 = self.width - 1 - c.0;
+        }
+    }
+
+    // Mirrors top to bottom
+    pub fn flip_v(&mut self) {
+        for (c, _) in self.cells.iter_mut() {
+            c.1 = self.height - 1 - c.1;
+        }
+    }
+
+    // Pastes with the upper left corner at the given cell
+    pub fn paste(&self, grid: &mut dyn Grid<i64>, at: Coord<i64>, mode: PasteMode) {
+        if mode == PasteMode::Overwrite {
+            let lr = at.offset(Coord(self.width - 1, self.height - 1));
+            for c in grid.cells_in(at, lr) {
+                grid.kill(c);
+            }
+        }
+        for (c, s) in &self.cells {
+            let c = c.offset(at);
+            if mode == PasteMode::Xor && grid.get(c).is_some() {
+                grid.kill(c);
+            } else {
+                grid.set(c, *s);
+            }
+        }
+    }
+}
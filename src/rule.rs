@@ -0,0 +1,523 @@
+use std::fmt;
+use std::path::Path;
+use std::str::FromStr;
+use std::sync::Arc;
+
+use crate::neighborhood::Neighborhood;
+use crate::state::State;
+use crate::table::{self, Table};
+use crate::topology::Topology;
+
+// Rule in B/S notation, optionally followed by the universe topology. Birth and survival are
+// looked up by the neighborhood configuration, a bitmask of the alive neighbors going row by
+// row from the upper left one: NW = 1, N = 2, NE = 4, W = 8, E = 16, SW = 32, S = 64, SE = 128.
+// This covers both the outer totalistic rules and the isotropic non-totalistic ones given in
+// Hensel notation (B2n3/S23-q). Generations rules (B/S/C) have more than two states: a cell
+// which fails to survive goes through the refractory states 2 to C - 1 before it dies, and
+// only the cells in state 1 count as alive neighbors. The von Neumann and hexagonal
+// neighborhoods mask out some of the bits of the configuration. Larger than Life rules
+// count the neighbors within a larger radius instead, see Range. Block rules replace each
+// 2x2 block of the Margolus neighborhood at once, see blocks(). Any other rule is given by
+// a Golly rule table, see Table.
+#[derive(Clone, PartialEq)]
+pub struct Rule {
+    birth: [bool; 256],
+    survival: [bool; 256],
+    states: u8,
+    neighborhood: Neighborhood,
+    range: Option<Range>,
+    blocks: Option<[u8; 16]>,
+    table: Option<Arc<Table>>,
+    topology: Topology,
+}
+
+// Larger than Life parameters in Golly notation ("R5,C0,M1,S34..58,B34..45,NM"): the alive
+// cells within the radius are counted, including the middle one if requested, and a cell is
+// born or survives when the count falls into the inclusive interval.
+#[derive(Copy, Clone, PartialEq)]
+pub struct Range {
+    pub radius: u32,
+    pub middle: bool,
+    pub birth: (u32, u32),
+    pub survival: (u32, u32),
+}
+
+// Largest radius accepted, as in Golly
+const MAX_RADIUS: u32 = 500;
+
+// Well known block rules
+const BLOCK_RULES: [(&str, &str); 3] = [
+    ("CRITTERS", "15;14;13;3;11;5;6;1;7;9;10;2;12;4;8;0"),
+    ("BBM", "0;8;4;3;2;5;9;7;1;6;10;11;12;13;14;15"),
+    ("TRON", "15;1;2;3;4;5;6;7;8;9;10;11;12;13;14;0"),
+];
+
+// Hensel letters for 1 to 4 alive neighbors and their representative configurations, as in
+// Golly. The configurations for 5 to 8 neighbors are the complements of those for 8 - n.
+const LETTERS: [&str; 5] = ["", "ce", "ceaikn", "ceaiknjqry", "ceaiknjqrytwz"];
+const NEIGHBORHOODS: [&[u8]; 5] = [
+    &[0],
+    &[1, 2],
+    &[5, 10, 3, 24, 17, 36],
+    &[37, 26, 11, 7, 50, 13, 14, 38, 25, 49],
+    &[165, 90, 15, 29, 51, 39, 58, 54, 27, 53, 57, 46, 60],
+];
+
+// Offsets of the neighbors in the order of the configuration bits
+const OFFSETS: [(i32, i32); 8] = [(-1, -1), (0, -1), (1, -1), (-1, 0), (1, 0), (-1, 1), (0, 1), (1, 1)];
+
+fn transform(config: u8, f: impl Fn((i32, i32)) -> (i32, i32)) -> u8 {
+    let mut res = 0;
+    for (i, o) in OFFSETS.iter().enumerate() {
+        if config & (1 << i) != 0 {
+            res |= 1 << OFFSETS.iter().position(|p| *p == f(*o)).unwrap();
+        }
+    }
+    res
+}
+
+// The configuration under all the rotations and reflections
+fn symmetries(config: u8) -> [u8; 8] {
+    let mut res = [config; 8];
+    for i in 1..8 {
+        res[i] = if i == 4 { transform(config, |(x, y)| (-x, y)) } else { transform(res[i - 1], |(x, y)| (-y, x)) };
+    }
+    res
+}
+
+// Letters of the given number of neighbors paired with all their configurations
+fn letter_classes(n: u32) -> Vec<(char, Vec<u8>)> {
+    let (letters, invert) = if n > 4 { (LETTERS[8 - n as usize], true) } else { (LETTERS[n as usize], false) };
+    let reps = NEIGHBORHOODS[if invert { 8 - n as usize } else { n as usize }];
+    reps.iter().zip(letters.chars().chain([' '])).map(|(r, l)| {
+        let mut configs = symmetries(if invert { !r } else { *r }).to_vec();
+        configs.sort();
+        configs.dedup();
+        (l, configs)
+    }).collect()
+}
+
+impl Rule {
+    pub fn conway() -> Rule {
+        "B3/S23".parse().unwrap()
+    }
+
+    pub fn with_table(table: Table) -> Rule {
+        Rule {
+            birth: [false; 256],
+            survival: [false; 256],
+            states: table.states(),
+            neighborhood: table.neighborhood(),
+            range: None,
+            blocks: None,
+            table: Some(Arc::new(table)),
+            topology: Topology::Infinite,
+        }
+    }
+
+    // Birth and survival by the number of alive neighbors, only meaningful for totalistic rules
+    pub fn birth(&self, n: u8) -> bool {
+        self.birth[(1 << n) - 1]
+    }
+
+    pub fn survival(&self, n: u8) -> bool {
+        self.survival[(1 << n) - 1]
+    }
+
+    pub fn next(&self, alive: bool, n: u8) -> bool {
+        if alive { self.survival(n) } else { self.birth(n) }
+    }
+
+    // Whether only the number of alive neighbors matters, not their arrangement
+    pub fn is_totalistic(&self) -> bool {
+        (0..256).all(|c: usize| {
+            let n = (1 << c.count_ones()) - 1;
+            self.birth[c] == self.birth[n] && self.survival[c] == self.survival[n]
+        })
+    }
+
+    // Plain B/S rule of the Moore neighborhood on the infinite plane, the only kind of rule
+    // the backends other than the sparse map can run
+    pub fn is_plain_life_like(&self) -> bool {
+        self.states == 2 && self.is_totalistic() && self.table.is_none() && self.blocks.is_none() && self.range.is_none()
+            && self.neighborhood == Neighborhood::Moore && self.topology == Topology::Infinite
+    }
+
+    pub fn states(&self) -> u8 {
+        self.states
+    }
+
+    // Next state of a cell with the given neighborhood configuration, None meaning dead
+    pub fn next_state(&self, state: Option<State>, config: u8) -> Option<State> {
+        self.advance(state, self.birth[config as usize], self.survival[config as usize])
+    }
+
+    // Next state of a cell under a Larger than Life rule, given the number of alive cells in
+    // its neighborhood including the cell itself
+    pub fn next_state_count(&self, state: Option<State>, count: u32) -> Option<State> {
+        let range = self.range.expect("Not a Larger than Life rule");
+        let count = if !range.middle && state == Some(State::ALIVE) { count - 1 } else { count };
+        let within = |(min, max): (u32, u32)| count >= min && count <= max;
+        self.advance(state, within(range.birth), within(range.survival))
+    }
+
+    // Next state of a cell under a rule table, given the states of its neighbors (0 for dead)
+    // in the order of the configuration bits
+    pub fn next_state_table(&self, state: Option<State>, neighbors: &[u8; 8]) -> Option<State> {
+        let table = self.table.as_ref().expect("Not a rule table");
+        match table.next(state.map_or(0, |s| s.0), neighbors) {
+            0 => None,
+            s => Some(State(s)),
+        }
+    }
+
+    fn advance(&self, state: Option<State>, birth: bool, survival: bool) -> Option<State> {
+        match state {
+            None if birth => Some(State::ALIVE),
+            None => None,
+            Some(State::ALIVE) if survival => Some(State::ALIVE),
+            Some(State(s)) if s + 1 < self.states => Some(State(s + 1)),
+            Some(_) => None,
+        }
+    }
+
+    pub fn neighborhood(&self) -> Neighborhood {
+        self.neighborhood
+    }
+
+    pub fn range(&self) -> Option<Range> {
+        self.range
+    }
+
+    // Table of a block rule, indexed and giving the blocks as bitmasks of the alive cells:
+    // NW = 1, NE = 2, SW = 4, SE = 8. The blocks start at the even coordinates in the even
+    // generations and at the odd ones in the odd generations. Rules filling the empty blocks
+    // (Critters, Tron) empty the full ones in turn; they are run on the complemented board
+    // every other generation, so that the empty space stays empty.
+    pub fn blocks(&self, odd: bool) -> Option<[u8; 16]> {
+        let blocks = self.blocks?;
+        if blocks[0] == 0 {
+            return Some(blocks);
+        }
+        Some(std::array::from_fn(|b| if odd { blocks[15 - b] } else { 15 - blocks[b] }))
+    }
+
+    pub fn table(&self) -> Option<&Table> {
+        self.table.as_deref()
+    }
+
+    // Distance of the farthest neighbor
+    pub fn radius(&self) -> u32 {
+        self.range.map_or(1, |r| r.radius)
+    }
+
+    pub fn topology(&self) -> Topology {
+        self.topology
+    }
+
+    pub fn set_topology(&mut self, topology: Topology) {
+        self.topology = topology;
+    }
+}
+
+impl Default for Rule {
+    fn default() -> Rule {
+        Rule::conway()
+    }
+}
+
+// Parses the neighbor counts, each optionally followed by Hensel letters selecting some of
+// the configurations ("2n"), or by a minus and the letters of the excluded ones ("2-an")
+fn parse_counts(s: &str) -> Result<[bool; 256], String> {
+    let mut table = [false; 256];
+    let mut chars = s.chars().peekable();
+    while let Some(c) = chars.next() {
+        let n = match c.to_digit(9) {
+            Some(n) => n,
+            None => return Err(format!("Invalid neighbor count '{}'", c)),
+        };
+        let exclude = chars.next_if_eq(&'-').is_some();
+        let mut letters = String::new();
+        while let Some(l) = chars.next_if(|l| l.is_ascii_alphabetic()) {
+            letters.push(l.to_ascii_lowercase());
+        }
+        let classes = letter_classes(n);
+        if let Some(l) = letters.chars().find(|l| !classes.iter().any(|(cl, _)| cl == l)) {
+            return Err(format!("Invalid neighborhood '{}{}'", n, l));
+        }
+        if exclude && letters.is_empty() {
+            return Err(format!("Missing neighborhood letters after '{}-'", n));
+        }
+        for (l, configs) in classes {
+            if letters.is_empty() || letters.contains(l) != exclude {
+                for c in configs {
+                    table[c as usize] = true;
+                }
+            }
+        }
+    }
+    Ok(table)
+}
+
+fn write_counts(f: &mut fmt::Formatter, table: &[bool; 256]) -> fmt::Result {
+    for n in 0..9 {
+        let classes = letter_classes(n);
+        let (set, unset): (Vec<_>, Vec<_>) = classes.iter().partition(|(_, configs)| table[configs[0] as usize]);
+        if set.is_empty() {
+            continue;
+        }
+        write!(f, "{}", n)?;
+        if unset.is_empty() {
+            continue;
+        }
+        if unset.len() < set.len() {
+            write!(f, "-")?;
+        }
+        for (l, _) in if unset.len() < set.len() { unset } else { set } {
+            write!(f, "{}", l)?;
+        }
+    }
+    Ok(())
+}
+
+fn parse_states(s: &str) -> Result<u8, String> {
+    match s.parse::<u8>() {
+        Ok(n) if n >= 2 => Ok(n),
+        _ => Err(format!("Invalid number of states '{}'", s)),
+    }
+}
+
+fn parse_interval(s: &str) -> Result<(u32, u32), String> {
+    let interval = s.split_once("..").and_then(|(min, max)| Some((min.parse().ok()?, max.parse().ok()?)));
+    match interval {
+        Some((min, max)) if min <= max => Ok((min, max)),
+        _ => Err(format!("Invalid interval '{}'", s)),
+    }
+}
+
+// Parses the comma separated Larger than Life parameters, all of which are required
+fn parse_range(s: &str, topology: Topology) -> Result<Rule, String> {
+    let (mut radius, mut states, mut middle, mut survival, mut birth, mut neighborhood) = (None, None, None, None, None, None);
+    for p in s.split(',').map(|p| p.trim()) {
+        let value = p.get(1..).unwrap_or("");
+        let number = || value.parse::<u32>().map_err(|_| format!("Invalid parameter '{}'", p));
+        let duplicate = match p.chars().next() {
+            Some('R') => radius.replace(number()?).is_some(),
+            Some('C') => states.replace(number()?).is_some(),
+            Some('M') => middle.replace(number()?).is_some(),
+            Some('S') => survival.replace(parse_interval(value)?).is_some(),
+            Some('B') => birth.replace(parse_interval(value)?).is_some(),
+            Some('N') => neighborhood.replace(match value {
+                "M" => Neighborhood::Moore,
+                "N" => Neighborhood::VonNeumann,
+                "C" => Neighborhood::Circular,
+                _ => return Err(format!("Unknown neighborhood '{}'", value)),
+            }).is_some(),
+            _ => return Err(format!("Invalid parameter '{}'", p)),
+        };
+        if duplicate {
+            return Err(format!("Duplicate parameter '{}'", p));
+        }
+    }
+    let (radius, states, middle, survival, birth, neighborhood) = match (radius, states, middle, survival, birth, neighborhood) {
+        (Some(r), Some(c), Some(m), Some(s), Some(b), Some(n)) => (r, c, m, s, b, n),
+        _ => return Err(format!("Missing Larger than Life parameters in '{}'", s)),
+    };
+    if !(1..=MAX_RADIUS).contains(&radius) {
+        return Err(format!("Radius must be between 1 and {}", MAX_RADIUS));
+    }
+    if middle > 1 {
+        return Err(format!("Invalid parameter 'M{}'", middle));
+    }
+    if birth.0 == 0 {
+        return Err("B0 rules are not supported".to_string());
+    }
+    Ok(Rule {
+        birth: [false; 256],
+        survival: [false; 256],
+        // C0 and C1 stand for the two state rules
+        states: match states {
+            0..=2 => 2,
+            3..=255 => states as u8,
+            _ => return Err(format!("Invalid number of states '{}'", states)),
+        },
+        neighborhood,
+        range: Some(Range { radius, middle: middle == 1, birth, survival }),
+        blocks: None,
+        table: None,
+        topology,
+    })
+}
+
+// Parses the 16 entries of a block rule separated by semicolons
+fn parse_blocks(s: &str, topology: Topology) -> Result<Rule, String> {
+    let entries: Vec<u8> = s.split(';').map(|e| e.trim().parse().ok().filter(|e| *e < 16))
+        .collect::<Option<_>>().ok_or(format!("Invalid block rule '{}'", s))?;
+    let blocks: [u8; 16] = entries.try_into().map_err(|_| "Block rule needs 16 entries".to_string())?;
+    if (0..16).any(|b| !blocks.contains(&b)) {
+        return Err("Block rule must be a permutation of 0 to 15".to_string());
+    }
+    if blocks[0] != 0 && (blocks[0] != 15 || blocks[15] != 0) {
+        return Err("Block rule must keep the empty blocks empty or swap them with the full ones".to_string());
+    }
+    if topology.size().is_some_and(|(w, h)| w % 2 != 0 || h % 2 != 0) {
+        return Err("Block rules need even universe dimensions".to_string());
+    }
+    // The edges of a sphere are glued crosswise, splitting the blocks along them
+    if let Topology::Sphere(_) = topology {
+        return Err("Block rules are not supported on spheres".to_string());
+    }
+    Ok(Rule {
+        birth: [false; 256],
+        survival: [false; 256],
+        states: 2,
+        neighborhood: Neighborhood::Moore,
+        range: None,
+        blocks: Some(blocks),
+        table: None,
+        topology,
+    })
+}
+
+// Accepts "B36/S23", "B36S23", "S23/B36" and the legacy "23/36" (survival/birth) forms,
+// Generations rules add the number of states: "B2/S/C3" or the legacy "/2/3". The "V" and
+// "H" suffixes select the von Neumann and hexagonal neighborhoods. Rules starting with the
+// radius ("R5,...") are Larger than Life ones, block rules are given in MCell notation
+// ("MS,D0;8;4;3;2;5;9;7;1;6;10;11;12;13;14;15") or by name ("BBM").
+fn parse_rule(s: &str, topology: Topology) -> Result<Rule, String> {
+    let mut s = s.trim().to_uppercase();
+    if s.starts_with('R') && s[1..].starts_with(|c: char| c.is_ascii_digit()) {
+        return parse_range(&s, topology);
+    }
+    if let Some(blocks) = s.strip_prefix("MS,D") {
+        return parse_blocks(blocks, topology);
+    }
+    if let Some((_, blocks)) = BLOCK_RULES.iter().find(|(name, _)| *name == s) {
+        return parse_blocks(blocks, topology);
+    }
+    let neighborhood = match s.chars().last() {
+        Some('V') => Neighborhood::VonNeumann,
+        Some('H') => Neighborhood::Hexagonal,
+        _ => Neighborhood::Moore,
+    };
+    if neighborhood != Neighborhood::Moore {
+        s.pop();
+    }
+    let parts: Vec<&str> = match s.find('/') {
+        Some(_) => s.split('/').collect(),
+        None => match s.find('S') {
+            Some(i) if s.starts_with('B') => vec![&s[..i], &s[i..]],
+            _ => return Err(format!("Cannot parse rule '{}'", s)),
+        },
+    };
+    if parts.len() > 3 {
+        return Err(format!("Cannot parse rule '{}'", s));
+    }
+
+    let prefixed = |p: &&str| p.starts_with(['B', 'S', 'C', 'G']);
+    let (mut b, mut sv, mut c) = (None, None, None);
+    if parts.iter().all(prefixed) {
+        for p in &parts {
+            let field = match p.chars().next() {
+                Some('B') => &mut b,
+                Some('S') => &mut sv,
+                _ => &mut c,
+            };
+            if field.replace(&p[1..]).is_some() {
+                return Err(format!("Cannot parse rule '{}'", s));
+            }
+        }
+    } else if parts.iter().any(prefixed) {
+        return Err(format!("Cannot parse rule '{}'", s));
+    } else {
+        (sv, b, c) = (parts.first().copied(), parts.get(1).copied(), parts.get(2).copied());
+    }
+    let (b, sv) = match (b, sv) {
+        (Some(b), Some(sv)) => (b, sv),
+        _ => return Err(format!("Cannot parse rule '{}'", s)),
+    };
+
+    let rule = Rule {
+        birth: parse_counts(b)?,
+        survival: parse_counts(sv)?,
+        states: c.map_or(Ok(2), parse_states)?,
+        neighborhood,
+        range: None,
+        blocks: None,
+        table: None,
+        topology,
+    };
+    if rule.birth(0) {
+        return Err("B0 rules are not supported".to_string());
+    }
+    if neighborhood != Neighborhood::Moore && !rule.is_totalistic() {
+        return Err("Hensel notation is supported only for the Moore neighborhood".to_string());
+    }
+    if let Some(n) = (neighborhood.size() + 1..9).find(|n| rule.birth(*n) || rule.survival(*n)) {
+        return Err(format!("Neighbor count {} is too large for the neighborhood", n));
+    }
+    Ok(rule)
+}
+
+impl FromStr for Rule {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Rule, String> {
+        Rule::parse_in(s, None)
+    }
+}
+
+impl Rule {
+    // Other than the rules in B/S and Larger than Life notation, a path to a .rule file or
+    // the name of one found by table::find_rule ("Wireworld") may be given. Rules of pattern
+    // files are looked up in the directory of the file first.
+    pub fn parse_in(s: &str, dir: Option<&Path>) -> Result<Rule, String> {
+        let (s, topology) = match s.split_once(':') {
+            Some((s, t)) => (s, t.parse()?),
+            None => (s, Topology::Infinite),
+        };
+        let s = s.trim();
+        let mut rule = if s.ends_with(".rule") {
+            table::read_rule_file(s)?
+        } else {
+            match parse_rule(s, topology) {
+                Ok(rule) => return Ok(rule),
+                Err(e) if !s.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_') => return Err(e),
+                Err(e) => table::find_rule(s, dir).unwrap_or(Err(e))?,
+            }
+        };
+        rule.set_topology(topology);
+        Ok(rule)
+    }
+}
+
+impl fmt::Display for Rule {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if let Some(table) = &self.table {
+            write!(f, "{}", table.name())?;
+        } else if let Some(blocks) = self.blocks {
+            write!(f, "MS,D{}", blocks.map(|b| b.to_string()).join(";"))?;
+        } else if let Some(r) = self.range {
+            let n = match self.neighborhood {
+                Neighborhood::VonNeumann => 'N',
+                Neighborhood::Circular => 'C',
+                _ => 'M',
+            };
+            write!(f, "R{},C{},M{},S{}..{},B{}..{},N{}", r.radius, if self.states > 2 { self.states } else { 0 },
+                r.middle as u8, r.survival.0, r.survival.1, r.birth.0, r.birth.1, n)?;
+        } else {
+            write!(f, "B")?;
+            write_counts(f, &self.birth)?;
+            write!(f, "/S")?;
+            write_counts(f, &self.survival)?;
+            if self.states > 2 {
+                write!(f, "/C{}", self.states)?;
+            }
+            write!(f, "{}", self.neighborhood.suffix())?;
+        }
+        if self.topology != Topology::Infinite {
+            write!(f, ":{}", self.topology)?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,44 @@
+use std::cmp::{min, max};
+
+use crate::coord::Coord;
+
+// Cells of a straight line between two cells, both ends included (Bresenham)
+pub fn line(from: Coord<i64>, to: Coord<i64>) -> Vec<Coord<i64>> {
+    let (dx, dy) = ((to.0 - from.0).abs(), -(to.1 - from.1).abs());
+    let (sx, sy) = ((to.0 - from.0).signum(), (to.1 - from.1).signum());
+    let mut err = dx + dy;
+    let mut c = from;
+    let mut res = vec![c];
+    while c.0 != to.0 || c.1 != to.1 {
+        let e2 = 2 * err;
+        if e2 >= dy {
+            err += dy;
+            c.0 += sx;
+        }
+        if e2 <= dx {
+            err += dx;
+            c.1 += sy;
+        }
+        res.push(c);
+    }
+    res
+}
+
+// Cells of a rectangle outline with the given opposite corners
+pub fn rect(a: Coord<i64>, b: Coord<i64>) -> Vec<Coord<i64>> {
+    let (ul, lr) = (Coord(min(a.0, b.0), min(a.1, b.1)), Coord(max(a.0, b.0), max(a.1, b.1)));
+    let mut res = Vec::new();
+    for x in ul.0..=lr.0 {
+        res.push(Coord(x, ul.1));
+        if lr.1 != ul.1 {
+            res.push(Coord(x, lr.1));
+        }
+    }
+    for y in (ul.1 + 1)..lr.1 {
+        res.push(Coord(ul.0, y));
+        if lr.0 != ul.0 {
+            res.push(Coord(lr.0, y));
+        }
+    }
+    res
+}
@@ -1,6 +1,9 @@
+// Cell state. Dead cells are not stored, so zero is never used: 1 is the ordinary alive
+// cell and the higher values belong to multi-state rules and patterns.
 #[derive(Copy, Clone)]
 #[derive(PartialEq)]
-pub enum State {
-    Alive,
-    Dying
+pub struct State(pub u8);
+
+impl State {
+    pub const ALIVE: State = State(1);
 }
@@ -0,0 +1,6 @@
+#[derive(Copy, Clone, Default)]
+pub struct Stats {
+    pub births: u64,
+    pub deaths: u64,
+    pub population: u64,
+}
@@ -0,0 +1,415 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::RwLock;
+
+use crate::neighborhood::Neighborhood;
+use crate::rule::Rule;
+
+// Golly rule table, the @TABLE section of a .rule file. Transitions lead from the state of a
+// cell and its neighbors to the next state. They are tried in the order of the file and the
+// first matching one wins, a cell no transition matches keeps its state. Inputs are states or
+// variables standing for sets of states; a variable used more than once in a transition has
+// to match the same state everywhere, and the output may be one of the input variables.
+// Symmetries let a transition match the rotated or reflected neighborhoods as well.
+pub struct Table {
+    name: String,
+    states: u8,
+    neighborhood: Neighborhood,
+    symmetry: Symmetry,
+    transitions: Vec<Transition>,
+    vars: usize,
+    colors: Vec<Option<(u8, u8, u8)>>,
+    // Next states already looked up, by the state of the cell followed by its neighbors. Once
+    // the pattern settles lookups are almost always found, so threads mostly share the lock.
+    cache: RwLock<HashMap<[u8; 9], u8>>,
+}
+
+#[derive(PartialEq)]
+enum Symmetry {
+    // Orders of the neighbors to try
+    Orders(Vec<Vec<usize>>),
+    // Any order of the neighbors
+    Permute,
+}
+
+#[derive(PartialEq)]
+struct Input {
+    states: Vec<u8>,
+    var: Option<usize>,
+}
+
+#[derive(PartialEq)]
+enum Output {
+    State(u8),
+    Var(usize),
+}
+
+#[derive(PartialEq)]
+struct Transition {
+    inputs: Vec<Input>,
+    output: Output,
+}
+
+// Neighbors in the order of Golly, clockwise from the north one, as indices into the
+// configuration bits of Rule (NW, N, NE, W, E, SW, S, SE)
+const MOORE: [usize; 8] = [1, 2, 4, 7, 6, 5, 3, 0];
+const VON_NEUMANN: [usize; 4] = [1, 4, 6, 3];
+
+// Binds the variable of the input to the value, fails if it does not match
+fn bind(input: &Input, value: u8, bound: &mut [Option<u8>]) -> bool {
+    if !input.states.contains(&value) {
+        return false;
+    }
+    match input.var {
+        Some(v) if bound[v].is_some() => bound[v] == Some(value),
+        Some(v) => {
+            bound[v] = Some(value);
+            true
+        },
+        None => true,
+    }
+}
+
+// Matches the inputs against the values in any order, each value used once
+fn permute(inputs: &[Input], values: &[u8], used: &mut [bool], bound: &mut Vec<Option<u8>>) -> bool {
+    let (input, rest) = match inputs.split_first() {
+        Some(s) => s,
+        None => return true,
+    };
+    for i in 0..values.len() {
+        if used[i] {
+            continue;
+        }
+        let saved = bound.clone();
+        if bind(input, values[i], bound) {
+            used[i] = true;
+            if permute(rest, values, used, bound) {
+                return true;
+            }
+            used[i] = false;
+        }
+        *bound = saved;
+    }
+    false
+}
+
+// Orders of the neighbors generated by the rotations by the step and optionally the reflection
+fn orders(n: usize, step: usize, reflect: bool) -> Vec<Vec<usize>> {
+    let mut res = Vec::new();
+    for r in (0..n).step_by(step) {
+        res.push((0..n).map(|i| (i + r) % n).collect::<Vec<_>>());
+        if reflect {
+            res.push((0..n).map(|i| (n - i + r) % n).collect());
+        }
+    }
+    res
+}
+
+fn parse_symmetry(s: &str, n: usize) -> Result<Symmetry, String> {
+    let orders = match s {
+        "none" => orders(n, n, false),
+        "rotate2" => orders(n, n / 2, false),
+        "rotate4" => orders(n, n / 4, false),
+        "rotate4reflect" => orders(n, n / 4, true),
+        "rotate8" if n == 8 => orders(n, 1, false),
+        "rotate8reflect" if n == 8 => orders(n, 1, true),
+        "reflect" | "reflect_horizontal" => orders(n, n, true),
+        "permute" => return Ok(Symmetry::Permute),
+        _ => return Err(format!("Unsupported symmetries '{}'", s)),
+    };
+    Ok(Symmetry::Orders(orders))
+}
+
+// Splits a transition at the commas outside of the braces, the compact form without commas
+// has a single character per state
+fn tokens(line: &str) -> Vec<String> {
+    if !line.contains([',', '{']) {
+        return line.chars().filter(|c| !c.is_whitespace()).map(String::from).collect();
+    }
+    let mut res = vec![String::new()];
+    let mut depth = 0;
+    for c in line.chars() {
+        match c {
+            ',' if depth == 0 => res.push(String::new()),
+            _ => {
+                depth += (c == '{') as i32 - (c == '}') as i32;
+                res.last_mut().unwrap().push(c);
+            },
+        }
+    }
+    res.iter().map(|t| t.trim().to_string()).collect()
+}
+
+struct Parser {
+    states: Option<u8>,
+    neighborhood: Neighborhood,
+    symmetry: Option<Symmetry>,
+    vars: HashMap<String, (usize, Vec<u8>)>,
+    transitions: Vec<Transition>,
+}
+
+impl Parser {
+    fn states(&self) -> Result<u8, String> {
+        self.states.ok_or("Missing n_states".to_string())
+    }
+
+    fn state(&self, s: &str) -> Result<u8, String> {
+        match s.parse::<u8>() {
+            Ok(n) if n < self.states()? => Ok(n),
+            _ => Err(format!("Invalid state '{}'", s)),
+        }
+    }
+
+    // States of a number, a variable or a set in braces
+    fn set(&self, s: &str) -> Result<Vec<u8>, String> {
+        if let Some(inner) = s.strip_prefix('{').and_then(|s| s.strip_suffix('}')) {
+            let mut res = Vec::new();
+            for e in inner.split(',') {
+                res.extend(self.set(e.trim())?);
+            }
+            return Ok(res);
+        }
+        match self.vars.get(s) {
+            Some((_, states)) => Ok(states.clone()),
+            None => Ok(vec![self.state(s)?]),
+        }
+    }
+
+    fn line(&mut self, line: &str) -> Result<(), String> {
+        match line.split_once(':') {
+            Some(("n_states", v)) => {
+                self.states = Some(v.trim().parse().ok().filter(|s| *s >= 2)
+                    .ok_or(format!("Unsupported number of states '{}'", v.trim()))?);
+            },
+            Some(("neighborhood", v)) => {
+                self.neighborhood = match v.trim() {
+                    "Moore" => Neighborhood::Moore,
+                    "vonNeumann" => Neighborhood::VonNeumann,
+                    v => return Err(format!("Unsupported neighborhood '{}'", v)),
+                };
+            },
+            Some(("symmetries", v)) => self.symmetry = Some(parse_symmetry(v.trim(), self.neighborhood.size() as usize)?),
+            _ => match line.strip_prefix("var ") {
+                Some(def) => self.var(def)?,
+                None => self.transition(line)?,
+            },
+        }
+        Ok(())
+    }
+
+    fn var(&mut self, def: &str) -> Result<(), String> {
+        let (name, value) = def.split_once('=').ok_or(format!("Invalid variable '{}'", def))?;
+        let states = self.set(value.trim())?;
+        let id = self.vars.get(name.trim()).map_or(self.vars.len(), |v| v.0);
+        self.vars.insert(name.trim().to_string(), (id, states));
+        Ok(())
+    }
+
+    fn transition(&mut self, line: &str) -> Result<(), String> {
+        let n = self.neighborhood.size() as usize;
+        let tokens = tokens(line);
+        if tokens.len() != n + 2 {
+            return Err(format!("Transition '{}' needs {} states", line, n + 2));
+        }
+        let mut inputs = Vec::new();
+        for t in &tokens[..n + 1] {
+            inputs.push(Input { states: self.set(t)?, var: self.vars.get(t.as_str()).map(|v| v.0) });
+        }
+        let out = &tokens[n + 1];
+        let output = match self.vars.get(out.as_str()) {
+            Some((id, _)) if inputs.iter().any(|i| i.var == Some(*id)) => Output::Var(*id),
+            Some(_) => return Err(format!("Output variable '{}' is not bound", out)),
+            None => Output::State(self.state(out)?),
+        };
+        self.transitions.push(Transition { inputs, output });
+        Ok(())
+    }
+}
+
+impl Table {
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn states(&self) -> u8 {
+        self.states
+    }
+
+    pub fn neighborhood(&self) -> Neighborhood {
+        self.neighborhood
+    }
+
+    // Color given in the @COLORS section
+    pub fn color(&self, state: u8) -> Option<(u8, u8, u8)> {
+        self.colors.get(state as usize).copied().flatten()
+    }
+
+    fn apply(&self, t: &Transition, values: &[u8]) -> Option<u8> {
+        let mut bound = vec![None; self.vars];
+        if !bind(&t.inputs[0], values[0], &mut bound) {
+            return None;
+        }
+        let neighbors = &values[1..];
+        let matched = match &self.symmetry {
+            Symmetry::Permute => permute(&t.inputs[1..], neighbors, &mut vec![false; neighbors.len()], &mut bound),
+            Symmetry::Orders(orders) => orders.iter().any(|o| {
+                let mut b = bound.clone();
+                let found = t.inputs[1..].iter().zip(o).all(|(input, i)| bind(input, neighbors[*i], &mut b));
+                if found {
+                    bound = b;
+                }
+                found
+            }),
+        };
+        match t.output {
+            _ if !matched => None,
+            Output::State(s) => Some(s),
+            Output::Var(v) => bound[v],
+        }
+    }
+
+    // Next state of the cell, given the states of its neighbors in the order of the
+    // configuration bits of Rule
+    pub fn next(&self, state: u8, neighbors: &[u8; 8]) -> u8 {
+        let order: &[usize] = if self.neighborhood == Neighborhood::Moore { &MOORE } else { &VON_NEUMANN };
+        let mut key = [0; 9];
+        key[0] = state;
+        for (i, n) in order.iter().enumerate() {
+            key[i + 1] = neighbors[*n];
+        }
+        if let Some(next) = self.cache.read().unwrap().get(&key) {
+            return *next;
+        }
+        let values = &key[..order.len() + 1];
+        let next = self.transitions.iter().find_map(|t| self.apply(t, values)).unwrap_or(state);
+        self.cache.write().unwrap().insert(key, next);
+        next
+    }
+}
+
+impl PartialEq for Table {
+    fn eq(&self, other: &Table) -> bool {
+        self.name == other.name && self.states == other.states && self.neighborhood == other.neighborhood &&
+            self.symmetry == other.symmetry && self.transitions == other.transitions && self.colors == other.colors
+    }
+}
+
+// Parses the contents of a .rule file. Only the @RULE, @TABLE and @COLORS sections are used.
+pub fn parse_rule_file(text: &str) -> Result<Table, String> {
+    let mut name = None;
+    let mut section = "";
+    let mut parser = Parser { states: None, neighborhood: Neighborhood::Moore, symmetry: None, vars: HashMap::new(), transitions: Vec::new() };
+    let mut colors = Vec::new();
+    let mut gradient = None;
+    let mut has_table = false;
+    for (n, line) in text.lines().enumerate() {
+        let line = line.split('#').next().unwrap().trim();
+        if line.is_empty() {
+            continue;
+        }
+        if let Some(s) = line.strip_prefix('@') {
+            let (s, rest) = s.split_once(char::is_whitespace).unwrap_or((s, ""));
+            section = match s {
+                "RULE" => "RULE",
+                "TABLE" => "TABLE",
+                "COLORS" => "COLORS",
+                _ => "",
+            };
+            if section == "RULE" {
+                name = Some(rest.trim().to_string());
+            }
+            has_table |= section == "TABLE";
+            continue;
+        }
+        let res = match section {
+            "TABLE" => parser.line(line),
+            "COLORS" => {
+                let values: Vec<Option<u8>> = line.split_whitespace().map(|v| v.parse().ok()).collect();
+                match values[..] {
+                    [Some(s), Some(r), Some(g), Some(b)] => {
+                        if colors.len() <= s as usize {
+                            colors.resize(s as usize + 1, None);
+                        }
+                        colors[s as usize] = Some((r, g, b));
+                    },
+                    // Gradient from the first to the last live state
+                    [Some(r1), Some(g1), Some(b1), Some(r2), Some(g2), Some(b2)] => gradient = Some(([r1, g1, b1], [r2, g2, b2])),
+                    // Other lines only change the looks, so they are ignored
+                    _ => (),
+                }
+                Ok(())
+            },
+            _ => Ok(()),
+        };
+        res.map_err(|e| format!("Line {}: {}", n + 1, e))?;
+    }
+
+    let name = name.filter(|n| !n.is_empty()).ok_or("Missing @RULE name".to_string())?;
+    if !has_table {
+        return Err(format!("Rule {} has no @TABLE section", name));
+    }
+    let states = parser.states()?;
+    if let Some((from, to)) = gradient {
+        if colors.len() < states as usize {
+            colors.resize(states as usize, None);
+        }
+        for (s, color) in colors.iter_mut().enumerate().take(states as usize).skip(1) {
+            let t = if states > 2 { (s - 1) as f64 / (states - 2) as f64 } else { 0.0 };
+            let [r, g, b] = std::array::from_fn(|i| (from[i] as f64 + (to[i] as f64 - from[i] as f64) * t).round() as u8);
+            color.get_or_insert((r, g, b));
+        }
+    }
+    let n = parser.neighborhood.size() as usize;
+    let table = Table {
+        name,
+        states,
+        neighborhood: parser.neighborhood,
+        symmetry: parser.symmetry.unwrap_or(Symmetry::Orders(orders(n, n, false))),
+        vars: parser.vars.len(),
+        transitions: parser.transitions,
+        colors,
+        cache: RwLock::new(HashMap::new()),
+    };
+    // Only the cells near the pattern are computed, so the empty universe has to stay empty
+    if table.next(0, &[0; 8]) != 0 {
+        return Err(format!("Rule {} brings empty cells to life", table.name));
+    }
+    Ok(table)
+}
+
+pub fn read_rule_file<P: AsRef<Path>>(path: P) -> Result<Rule, String> {
+    let text = std::fs::read_to_string(&path).map_err(|e| format!("Cannot read {}: {}", path.as_ref().to_string_lossy(), e))?;
+    Ok(Rule::with_table(parse_rule_file(&text)?))
+}
+
+// Directories where rule files are looked up by name, the current directory if none are set.
+// Programs changing the current directory should set the one they started in.
+static RULE_DIRS: RwLock<Vec<PathBuf>> = RwLock::new(Vec::new());
+
+pub fn set_rule_dirs(dirs: Vec<PathBuf>) {
+    *RULE_DIRS.write().unwrap() = dirs;
+}
+
+// Looks for the rule file of the name, ignoring case, in the given directory (that of the
+// pattern file naming the rule), then in the rule directories, and in their rules
+// subdirectories. Returns None if there is no such file.
+pub fn find_rule(name: &str, dir: Option<&Path>) -> Option<Result<Rule, String>> {
+    let file = format!("{}.rule", name);
+    let mut dirs: Vec<PathBuf> = dir.into_iter().map(Path::to_path_buf).collect();
+    match RULE_DIRS.read().unwrap().as_slice() {
+        [] => dirs.push(PathBuf::from(".")),
+        set => dirs.extend_from_slice(set),
+    }
+    for dir in dirs.iter().flat_map(|d| [d.clone(), d.join("rules")]) {
+        let entries = match std::fs::read_dir(dir) {
+            Ok(entries) => entries,
+            Err(_) => continue,
+        };
+        for e in entries.filter_map(|e| e.ok()) {
+            if e.file_name().to_string_lossy().eq_ignore_ascii_case(&file) {
+                return Some(read_rule_file(e.path()));
+            }
+        }
+    }
+    None
+}
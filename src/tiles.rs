@@ -0,0 +1,253 @@
+use std::collections::{HashMap, HashSet};
+use std::cmp::{min, max};
+use std::thread;
+
+use crate::coord::Coord;
+use crate::grid::Grid;
+use crate::rule::Rule;
+use crate::state::State;
+use crate::stats::Stats;
+
+const TILE_BITS: u32 = 6;
+const TILE_SIZE: i64 = 1 << TILE_BITS;
+const TILE_MASK: i64 = TILE_SIZE - 1;
+
+// 64x64 cells, one u64 per row, bit x is the cell in column x
+type Tile = [u64; TILE_SIZE as usize];
+
+const EMPTY: Tile = [0; TILE_SIZE as usize];
+
+// Bit-packed grid backend, better suited for dense patterns than the sparse Map
+pub struct TileGrid {
+    tiles: HashMap<(i64, i64), Tile>,
+    rule: Rule,
+    generation: u64,
+    population: u64,
+    threads: usize,
+}
+
+impl Default for TileGrid {
+    fn default() -> TileGrid {
+        TileGrid::new()
+    }
+}
+
+fn split(coord: Coord<i64>) -> ((i64, i64), usize, u32) {
+    (
+        (coord.0 >> TILE_BITS, coord.1 >> TILE_BITS),
+        (coord.1 & TILE_MASK) as usize,
+        (coord.0 & TILE_MASK) as u32,
+    )
+}
+
+// Adds a one bit value to each of 64 bit-sliced 4 bit counters
+fn add(planes: &mut [u64; 4], v: u64) {
+    let mut carry = v;
+    for p in planes.iter_mut() {
+        let t = *p & carry;
+        *p ^= carry;
+        carry = t;
+    }
+}
+
+impl TileGrid {
+    pub fn new() -> TileGrid {
+        TileGrid {
+            tiles: HashMap::new(),
+            rule: Rule::default(),
+            generation: 0,
+            population: 0,
+            threads: 1,
+        }
+    }
+
+    // Masks of the cells having exactly n neighbors, for each n in the given set
+    fn count_mask(planes: &[u64; 4], counts: impl Fn(u8) -> bool) -> u64 {
+        let mut mask = 0;
+        for n in (0..9u8).filter(|n| counts(*n)) {
+            let mut eq = !0u64;
+            for (i, p) in planes.iter().enumerate() {
+                eq &= if n & (1 << i) != 0 { *p } else { !*p };
+            }
+            mask |= eq;
+        }
+        mask
+    }
+
+    fn step_tile(&self, key: (i64, i64)) -> Tile {
+        let around = |dx: i64, dy: i64| self.tiles.get(&(key.0 + dx, key.1 + dy)).unwrap_or(&EMPTY);
+        let (nw, n, ne) = (around(-1, -1), around(0, -1), around(1, -1));
+        let (w, c, e) = (around(-1, 0), around(0, 0), around(1, 0));
+        let (sw, s, se) = (around(-1, 1), around(0, 1), around(1, 1));
+
+        let last = TILE_SIZE as usize - 1;
+        // Row y of the 66x66 area around the tile, as (west bit, row, east bit)
+        let row = |y: isize| -> (u64, u64, u64) {
+            let (wt, t, et, y) = match y {
+                -1 => (nw, n, ne, last),
+                64 => (sw, s, se, 0),
+                _ => (w, c, e, y as usize),
+            };
+            (wt[y] >> 63, t[y], et[y] & 1)
+        };
+
+        let mut res = EMPTY;
+        for (y, r) in res.iter_mut().enumerate() {
+            let mut planes = [0u64; 4];
+            for dy in -1..=1 {
+                let (wb, row, eb) = row(y as isize + dy);
+                add(&mut planes, (row << 1) | wb);
+                add(&mut planes, (row >> 1) | (eb << 63));
+                if dy != 0 {
+                    add(&mut planes, row);
+                }
+            }
+            let alive = c[y];
+            let survive = TileGrid::count_mask(&planes, |n| self.rule.survival(n));
+            let birth = TileGrid::count_mask(&planes, |n| self.rule.birth(n));
+            *r = (alive & survive) | (!alive & birth);
+        }
+        res
+    }
+}
+
+impl Grid<i64> for TileGrid {
+    fn get(&self, coord: Coord<i64>) -> Option<State> {
+        let (key, y, x) = split(coord);
+        match self.tiles.get(&key) {
+            Some(t) if t[y] & (1 << x) != 0 => Some(State::ALIVE),
+            _ => None,
+        }
+    }
+
+    fn set(&mut self, coord: Coord<i64>, _s: State) {
+        let (key, y, x) = split(coord);
+        let t = self.tiles.entry(key).or_insert(EMPTY);
+        if t[y] & (1 << x) == 0 {
+            t[y] |= 1 << x;
+            self.population += 1;
+        }
+    }
+
+    fn kill(&mut self, coord: Coord<i64>) {
+        let (key, y, x) = split(coord);
+        if let Some(t) = self.tiles.get_mut(&key) {
+            if t[y] & (1 << x) != 0 {
+                t[y] &= !(1 << x);
+                self.population -= 1;
+            }
+        }
+    }
+
+    fn iter(&self) -> Box<dyn Iterator<Item = Coord<i64>> + '_> {
+        Box::new(self.tiles.iter().flat_map(|(key, t)| {
+            let (x0, y0) = (key.0 << TILE_BITS, key.1 << TILE_BITS);
+            t.iter().enumerate().flat_map(move |(y, row)| {
+                let row = *row;
+                (0..TILE_SIZE).filter(move |x| row & (1 << x) != 0).map(move |x| Coord(x0 + x, y0 + y as i64))
+            })
+        }))
+    }
+
+    fn clear(&mut self) {
+        self.tiles.clear();
+        self.population = 0;
+    }
+
+    fn dims(&self) -> (Coord<i64>, Coord<i64>) {
+        let mut ul = Coord(i64::MAX, i64::MAX);
+        let mut lr = Coord(i64::MIN, i64::MIN);
+        for (key, t) in self.tiles.iter() {
+            let (x0, y0) = (key.0 << TILE_BITS, key.1 << TILE_BITS);
+            let cols = t.iter().fold(0, |acc, r| acc | r);
+            if cols == 0 {
+                continue;
+            }
+            let first = t.iter().position(|r| *r != 0).unwrap() as i64;
+            let last = t.iter().rposition(|r| *r != 0).unwrap() as i64;
+            ul = Coord(min(ul.0, x0 + cols.trailing_zeros() as i64), min(ul.1, y0 + first));
+            lr = Coord(max(lr.0, x0 + 63 - cols.leading_zeros() as i64), max(lr.1, y0 + last));
+        }
+        if ul.0 > lr.0 {
+            panic!("Empty grid has no dimensions");
+        }
+        (ul, lr)
+    }
+
+    fn rule(&self) -> Rule {
+        self.rule.clone()
+    }
+
+    fn set_rule(&mut self, rule: Rule) -> Result<(), String> {
+        if !rule.is_plain_life_like() {
+            return Err("Tiled grid supports only two-state totalistic Moore rules on the infinite plane, use the sparse map for others".to_string());
+        }
+        self.rule = rule;
+        Ok(())
+    }
+
+    fn generation(&self) -> u64 {
+        self.generation
+    }
+
+    fn set_generation(&mut self, generation: u64) {
+        self.generation = generation;
+    }
+
+    fn population(&self) -> u64 {
+        self.population
+    }
+
+    fn step(&mut self) -> Stats {
+        let mut candidates: Vec<(i64, i64)> = Vec::new();
+        let mut seen: HashSet<(i64, i64)> = HashSet::new();
+        for key in self.tiles.keys() {
+            for dx in -1..=1 {
+                for dy in -1..=1 {
+                    let k = (key.0 + dx, key.1 + dy);
+                    if seen.insert(k) {
+                        candidates.push(k);
+                    }
+                }
+            }
+        }
+
+        let stepped: Vec<Tile> = if self.threads > 1 && candidates.len() > 1 {
+            let chunk = candidates.len().div_ceil(self.threads);
+            let this = &*self;
+            thread::scope(|s| {
+                let handles: Vec<_> = candidates.chunks(chunk)
+                    .map(|keys| s.spawn(move || keys.iter().map(|k| this.step_tile(*k)).collect::<Vec<_>>()))
+                    .collect();
+                handles.into_iter().flat_map(|h| h.join().unwrap()).collect()
+            })
+        } else {
+            candidates.iter().map(|k| self.step_tile(*k)).collect()
+        };
+
+        let mut stats = Stats::default();
+        let mut tiles = HashMap::new();
+        for (key, new) in candidates.into_iter().zip(stepped) {
+            let old = self.tiles.get(&key).unwrap_or(&EMPTY);
+            let mut pop = 0;
+            for (n, o) in new.iter().zip(old.iter()) {
+                stats.births += (n & !o).count_ones() as u64;
+                stats.deaths += (o & !n).count_ones() as u64;
+                pop += n.count_ones() as u64;
+            }
+            if pop > 0 {
+                tiles.insert(key, new);
+                stats.population += pop;
+            }
+        }
+
+        self.tiles = tiles;
+        self.population = stats.population;
+        self.generation += 1;
+        stats
+    }
+
+    fn set_threads(&mut self, threads: usize) {
+        self.threads = max(threads, 1);
+    }
+}
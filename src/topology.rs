@@ -0,0 +1,199 @@
+use std::fmt;
+use std::ops::{Add, AddAssign, Sub};
+use std::str::FromStr;
+
+use crate::coord::Coord;
+
+// Shape of the universe, given as a rule suffix in Golly notation (e.g. "B3/S23:T64,64").
+// As in Golly, a zero size makes a plane or a torus an infinite strip (e.g. "T0,64").
+// Bounded dimensions are centered at 0, starting at -width / 2 and -height / 2.
+// First and last coordinates along a dimension, None if it is unbounded
+pub type Extent<T> = Option<(T, T)>;
+
+#[derive(Copy, Clone, PartialEq)]
+pub enum Topology {
+    Infinite,
+    Plane(i32, i32),
+    Torus(i32, i32),
+    // The flag tells whether the top and bottom edges (true) or the left and right ones are twisted
+    KleinBottle(i32, i32, bool),
+    CrossSurface(i32, i32),
+    Sphere(i32),
+}
+
+impl Topology {
+    pub fn size(&self) -> Option<(i32, i32)> {
+        match *self {
+            Topology::Infinite => None,
+            Topology::Plane(w, h) | Topology::Torus(w, h) | Topology::KleinBottle(w, h, _) | Topology::CrossSurface(w, h) => Some((w, h)),
+            Topology::Sphere(n) => Some((n, n)),
+        }
+    }
+
+    // Extents of the columns and the rows
+    pub fn extents<T: From<i32>>(&self) -> (Extent<T>, Extent<T>) {
+        let extent = |n: i32| (n > 0).then(|| ((-(n / 2)).into(), (n - 1 - n / 2).into()));
+        match self.size() {
+            Some((w, h)) => (extent(w), extent(h)),
+            None => (None, None),
+        }
+    }
+
+    // Upper left and lower right cells of a universe bounded in both dimensions
+    pub fn bounds<T: From<i32> + Copy>(&self) -> Option<(Coord<T>, Coord<T>)> {
+        match self.extents::<T>() {
+            (Some((x0, x1)), Some((y0, y1))) => Some((Coord(x0, y0), Coord(x1, y1))),
+            _ => None,
+        }
+    }
+
+    pub fn contains<T: From<i32> + Ord + Copy>(&self, coord: Coord<T>) -> bool {
+        let (xs, ys) = self.extents::<T>();
+        let inside = |v: T, extent: Extent<T>| extent.is_none_or(|(lo, hi)| v >= lo && v <= hi);
+        inside(coord.0, xs) && inside(coord.1, ys)
+    }
+
+    // Maps a coordinate lying outside of the universe back into it, following the glued edges.
+    // Returns None if the cell does not exist (beyond the edge of a plane).
+    pub fn wrap<T>(&self, coord: Coord<T>) -> Option<Coord<T>> where
+        T: From<i32> + Add<Output = T> + Sub<Output = T> + AddAssign + Ord + Copy,
+    {
+        let zero: T = 0.into();
+        let one: T = 1.into();
+
+        // Returns the number of times the value crossed the edges, modulo 2
+        let fold = |v: &mut T, size: T| -> bool {
+            let mut flips = false;
+            while *v < zero {
+                *v += size;
+                flips = !flips;
+            }
+            while *v >= size {
+                *v = *v - size;
+                flips = !flips;
+            }
+            flips
+        };
+
+        // Planes and tori may be unbounded in one dimension, so their dimensions are handled apart
+        if let Topology::Plane(..) | Topology::Torus(..) = *self {
+            let (xs, ys) = self.extents::<T>();
+            let plane = matches!(*self, Topology::Plane(..));
+            let axis = |v: T, extent: Extent<T>| match extent {
+                None => Some(v),
+                Some((lo, hi)) if plane => (v >= lo && v <= hi).then_some(v),
+                Some((lo, hi)) => {
+                    let mut v = v - lo;
+                    fold(&mut v, hi - lo + one);
+                    Some(v + lo)
+                },
+            };
+            return Some(Coord(axis(coord.0, xs)?, axis(coord.1, ys)?));
+        }
+
+        let (ul, lr) = match self.bounds::<T>() {
+            Some(b) => b,
+            None => return Some(coord),
+        };
+        let (w, h): (T, T) = (lr.0 - ul.0 + 1.into(), lr.1 - ul.1 + 1.into());
+        let (mut x, mut y) = (coord.0 - ul.0, coord.1 - ul.1);
+
+        match *self {
+            Topology::Infinite | Topology::Plane(..) | Topology::Torus(..) => (),
+            Topology::KleinBottle(_, _, horizontal) => {
+                if horizontal {
+                    fold(&mut x, w);
+                    if fold(&mut y, h) {
+                        x = w - one - x;
+                    }
+                } else {
+                    fold(&mut y, h);
+                    if fold(&mut x, w) {
+                        y = h - one - y;
+                    }
+                }
+            },
+            Topology::CrossSurface(..) => {
+                let flip_x = fold(&mut y, h);
+                let flip_y = fold(&mut x, w);
+                if flip_x {
+                    x = w - one - x;
+                }
+                if flip_y {
+                    y = h - one - y;
+                }
+            },
+            Topology::Sphere(_) => {
+                // The top edge is glued to the left one and the bottom edge to the right one
+                let outside_x = x < zero || x >= w;
+                let outside_y = y < zero || y >= h;
+                if outside_x && outside_y {
+                    return None;
+                }
+                if x < zero {
+                    (x, y) = (y, zero);
+                } else if x >= w {
+                    (x, y) = (y, h - one);
+                } else if y < zero {
+                    (x, y) = (zero, x);
+                } else if y >= h {
+                    (x, y) = (w - one, x);
+                }
+            },
+        }
+        Some(Coord(x + ul.0, y + ul.1))
+    }
+}
+
+fn parse_size(s: &str) -> Result<i32, String> {
+    match s.parse::<i32>() {
+        Ok(n) if n >= 0 => Ok(n),
+        _ => Err(format!("Invalid universe size '{}'", s)),
+    }
+}
+
+impl FromStr for Topology {
+    type Err = String;
+
+    // Parses the part after the colon: "T64,64", "P30,20", "K64*,64", "C64,64", "S64"
+    fn from_str(s: &str) -> Result<Topology, String> {
+        let s = s.trim().to_uppercase();
+        let mut chars = s.chars();
+        let kind = match chars.next() {
+            Some(c) => c,
+            None => return Ok(Topology::Infinite),
+        };
+        let rest = chars.as_str();
+        let (ws, hs) = rest.split_once(',').unwrap_or((rest, rest));
+        let twist_h = ws.ends_with('*');
+        let twist_v = hs.ends_with('*') && !twist_h;
+        let w = parse_size(ws.trim_end_matches('*'))?;
+        let h = parse_size(hs.trim_end_matches('*'))?;
+
+        match kind {
+            'P' | 'T' if w == 0 && h == 0 => Ok(Topology::Infinite),
+            'P' => Ok(Topology::Plane(w, h)),
+            'T' => Ok(Topology::Torus(w, h)),
+            _ if w == 0 || h == 0 => Err("Only planes and tori can have an unbounded dimension (size 0)".to_string()),
+            'K' => Ok(Topology::KleinBottle(w, h, !twist_v)),
+            'C' => Ok(Topology::CrossSurface(w, h)),
+            'S' if w == h => Ok(Topology::Sphere(w)),
+            'S' => Err("Sphere must be square".to_string()),
+            _ => Err(format!("Unknown topology '{}'", kind)),
+        }
+    }
+}
+
+impl fmt::Display for Topology {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Topology::Infinite => Ok(()),
+            Topology::Plane(w, h) => write!(f, "P{},{}", w, h),
+            Topology::Torus(w, h) => write!(f, "T{},{}", w, h),
+            Topology::KleinBottle(w, h, true) => write!(f, "K{}*,{}", w, h),
+            Topology::KleinBottle(w, h, false) => write!(f, "K{},{}*", w, h),
+            Topology::CrossSurface(w, h) => write!(f, "C{},{}", w, h),
+            Topology::Sphere(n) => write!(f, "S{}", n),
+        }
+    }
+}
@@ -0,0 +1,58 @@
+use life::coord::Coord;
+use life::grid::Grid;
+use life::map::Map;
+use life::rule::Rule;
+use life::state::State;
+
+mod common;
+use common::{cells, soup};
+
+// Steps once and checks that the reported births and deaths add up to the new population
+fn step(grid: &mut Map<i64>) {
+    let before = grid.population();
+    let stats = grid.step();
+    assert_eq!(stats.population, grid.population());
+    assert_eq!(before + stats.births - stats.deaths, stats.population);
+}
+
+#[test]
+fn bbm_ball_crosses_torus_edges() {
+    let mut grid: Map<i64> = Map::new();
+    grid.set_rule("BBM:T10,10".parse().unwrap());
+    grid.set(Coord(0, 0), State::ALIVE);
+    let start = cells(&grid);
+    // A lone ball moves by one cell diagonally every generation
+    for _ in 0..20 {
+        step(&mut grid);
+        assert_eq!(grid.population(), 1);
+    }
+    assert_eq!(cells(&grid), start);
+}
+
+// Both halves of the Critters rule are their own inverse, so stepping again from the same
+// generation undoes a step
+#[test]
+fn critters_runs_back() {
+    for rule in ["Critters", "Critters:T10,10", "Critters:K10*,12", "Critters:T0,10"] {
+        let mut grid: Map<i64> = Map::new();
+        grid.set_rule(rule.parse().unwrap());
+        soup(&mut grid, 10, 3);
+        let start = cells(&grid);
+        for _ in 0..30 {
+            step(&mut grid);
+        }
+        assert_ne!(cells(&grid), start);
+        for generation in (0..30).rev() {
+            grid.set_generation(generation);
+            step(&mut grid);
+        }
+        assert_eq!(cells(&grid), start, "{}", rule);
+    }
+}
+
+#[test]
+fn block_rules_need_matching_universes() {
+    assert!("BBM:T10,10".parse::<Rule>().is_ok());
+    assert!("BBM:T11,10".parse::<Rule>().is_err());
+    assert!("BBM:S10".parse::<Rule>().is_err());
+}
@@ -0,0 +1,32 @@
+// Helpers shared by the tests, not all of them use every one
+#![allow(dead_code)]
+
+use life::coord::Coord;
+use life::grid::Grid;
+use life::state::State;
+
+// Random square soup of density about one half centered at (0, 0), the same for each seed
+pub fn soup(grid: &mut dyn Grid<i64>, size: i64, seed: u64) {
+    let mut seed = seed;
+    for y in -size / 2..size - size / 2 {
+        for x in -size / 2..size - size / 2 {
+            seed = seed.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+            if seed >> 63 == 1 {
+                grid.set(Coord(x, y), State::ALIVE);
+            }
+        }
+    }
+}
+
+// Alive cells, sorted
+pub fn cells(grid: &dyn Grid<i64>) -> Vec<(i64, i64)> {
+    let mut res: Vec<(i64, i64)> = grid.iter().map(|c| (c.0, c.1)).collect();
+    res.sort();
+    res
+}
+
+// Alive cells relative to the upper left corner of the bounding box, sorted
+pub fn shape(grid: &dyn Grid<i64>) -> Vec<(i64, i64)> {
+    let (ul, _) = grid.dims();
+    cells(grid).into_iter().map(|(x, y)| (x - ul.0, y - ul.1)).collect()
+}
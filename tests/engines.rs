@@ -0,0 +1,49 @@
+use life::grid::Grid;
+use life::hashlife::HashLife;
+use life::map::Map;
+use life::tiles::TileGrid;
+
+mod common;
+use common::{cells, soup};
+
+#[test]
+fn hashlife_matches_map() {
+    let exps = [0, 1, 3, 5];
+    let mut map: Map<i64> = Map::new();
+    soup(&mut map, 64, 7);
+    map.step_n(100);
+    let after_advance = cells(&map);
+    let mut after_step = Vec::new();
+    for exp in exps {
+        map.step_n(100 + (1 << exp) - map.generation());
+        after_step.push(cells(&map));
+    }
+
+    for (exp, expected) in exps.into_iter().zip(after_step) {
+        let mut hashlife = HashLife::new();
+        soup(&mut hashlife, 64, 7);
+        hashlife.set_step_exp(exp);
+        // Advancing goes by powers of two and leaves the step size alone
+        hashlife.advance(100);
+        assert_eq!(hashlife.generation(), 100);
+        assert_eq!(hashlife.step_exp(), Some(exp));
+        assert_eq!(cells(&hashlife), after_advance);
+        hashlife.step();
+        assert_eq!(hashlife.generation(), 100 + (1 << exp));
+        assert_eq!(cells(&hashlife), expected);
+    }
+}
+
+#[test]
+fn tiles_match_map() {
+    let mut map: Map<i64> = Map::new();
+    let mut tiles = TileGrid::new();
+    soup(&mut map, 64, 7);
+    soup(&mut tiles, 64, 7);
+    for _ in 0..10 {
+        map.step_n(15);
+        tiles.step_n(15);
+        assert_eq!(tiles.population(), map.population());
+        assert_eq!(cells(&tiles), cells(&map));
+    }
+}
@@ -0,0 +1,93 @@
+use life::coord::Coord;
+use life::grid::Grid;
+use life::hashlife::HashLife;
+use life::history::History;
+use life::map::Map;
+use life::state::State;
+
+mod common;
+use common::cells;
+
+// R-pentomino, which keeps changing for a long time
+fn pentomino(grid: &mut dyn Grid<i64>) {
+    for c in [(1, 0), (2, 0), (0, 1), (1, 1), (1, 2)] {
+        grid.set(Coord(c.0, c.1), State::ALIVE);
+    }
+}
+
+// Cells of the R-pentomino at the given generation
+fn expected(generation: u64) -> Vec<(i64, i64)> {
+    let mut grid: Map<i64> = Map::new();
+    pentomino(&mut grid);
+    grid.step_n(generation);
+    cells(&grid)
+}
+
+fn run(grid: &mut dyn Grid<i64>, history: &mut History<i64>, generations: u64) {
+    for _ in 0..generations {
+        history.record(grid);
+        grid.step();
+    }
+}
+
+#[test]
+fn back_and_rewind() {
+    let mut grid: Map<i64> = Map::new();
+    pentomino(&mut grid);
+    let mut history = History::new(3);
+    run(&mut grid, &mut history, 10);
+    assert_eq!(history.generations(), vec![0, 7, 8, 9]);
+
+    assert!(history.back(&mut grid));
+    assert_eq!(grid.generation(), 9);
+    assert_eq!(cells(&grid), expected(9));
+    assert!(history.back(&mut grid));
+    assert!(history.back(&mut grid));
+    assert_eq!(grid.generation(), 7);
+    // Older generations were dropped, so the next step back goes to the start
+    assert!(history.back(&mut grid));
+    assert_eq!(grid.generation(), 0);
+    assert_eq!(cells(&grid), expected(0));
+
+    run(&mut grid, &mut history, 5);
+    assert!(history.rewind(&mut grid));
+    assert_eq!(grid.generation(), 0);
+    assert_eq!(cells(&grid), expected(0));
+    assert_eq!(history.generations(), vec![0]);
+}
+
+#[test]
+fn jump() {
+    let mut grid: Map<i64> = Map::new();
+    pentomino(&mut grid);
+    let mut history = History::new(3);
+    run(&mut grid, &mut history, 20);
+
+    assert!(history.jump(&mut grid, 30).is_err());
+    assert_eq!(grid.generation(), 20);
+    // From a kept snapshot, then from the start
+    history.jump(&mut grid, 18).unwrap();
+    assert_eq!(grid.generation(), 18);
+    assert_eq!(cells(&grid), expected(18));
+    history.jump(&mut grid, 5).unwrap();
+    assert_eq!(grid.generation(), 5);
+    assert_eq!(cells(&grid), expected(5));
+
+    history.clear();
+    assert!(history.jump(&mut grid, 3).is_err());
+}
+
+#[test]
+fn jump_keeps_hashlife_step() {
+    let mut grid = HashLife::new();
+    pentomino(&mut grid);
+    grid.set_step_exp(4);
+    let mut history = History::new(16);
+    run(&mut grid, &mut history, 4);
+    assert_eq!(grid.generation(), 64);
+
+    history.jump(&mut grid, 37).unwrap();
+    assert_eq!(grid.generation(), 37);
+    assert_eq!(grid.step_exp(), Some(4));
+    assert_eq!(cells(&grid), expected(37));
+}
@@ -0,0 +1,72 @@
+use std::collections::HashMap;
+
+use life::coord::Coord;
+use life::map::Map;
+use life::rule::Rule;
+use life::state::State;
+
+mod common;
+use common::soup;
+
+// Next generation by counting the neighbors of every cell one by one
+fn brute_force(rule: &Rule, cells: &HashMap<(i64, i64), u8>) -> HashMap<(i64, i64), u8> {
+    let r = rule.radius() as i64;
+    let topology = rule.topology();
+    let neighborhood = rule.neighborhood();
+    let mut candidates: Vec<(i64, i64)> = match topology.bounds::<i64>() {
+        Some((ul, lr)) => (ul.1..=lr.1).flat_map(|y| (ul.0..=lr.0).map(move |x| (x, y))).collect(),
+        None => cells.keys()
+            .flat_map(|(x, y)| (-r..=r).flat_map(move |dy| (-r..=r).map(move |dx| Coord(x + dx, y + dy))))
+            .filter_map(|c| topology.wrap(c).map(|c| (c.0, c.1)))
+            .collect(),
+    };
+    candidates.sort();
+    candidates.dedup();
+
+    let mut next = HashMap::new();
+    for (x, y) in candidates {
+        let mut count = 0;
+        for dy in -r..=r {
+            let span = neighborhood.span(dy.unsigned_abs() as u32, r as u32) as i64;
+            for dx in -span..=span {
+                let alive = topology.wrap(Coord(x + dx, y + dy)).is_some_and(|c| cells.get(&(c.0, c.1)) == Some(&1));
+                count += alive as u32;
+            }
+        }
+        if let Some(s) = rule.next_state_count(cells.get(&(x, y)).map(|s| State(*s)), count) {
+            next.insert((x, y), s.0);
+        }
+    }
+    next
+}
+
+#[test]
+fn matches_brute_force() {
+    for (rule, threads) in [
+        ("R5,C0,M1,S34..58,B34..45,NM", 1),
+        ("R5,C0,M1,S34..58,B34..45,NM", 3),
+        ("R3,C0,M0,S4..9,B5..7,NN", 1),
+        ("R4,C0,M1,S15..30,B15..22,NC", 2),
+        ("R2,C4,M1,S4..9,B5..7,NM", 1),
+        ("R2,C0,M1,S4..9,B5..7,NM:T30,24", 2),
+        ("R2,C0,M1,S4..9,B5..7,NM:K30*,24", 1),
+        ("R2,C0,M1,S4..9,B5..7,NM:P30,24", 1),
+        ("R2,C0,M1,S4..9,B5..7,NM:T0,16", 2),
+        ("R2,C0,M1,S4..9,B5..7,NM:P20,0", 1),
+        ("R8,C0,M1,S40..80,B41..81,NM", 2),
+    ] {
+        let rule: Rule = rule.parse().unwrap();
+        let mut grid: Map<i64> = Map::new();
+        grid.set_rule(rule.clone());
+        grid.set_threads(threads);
+        soup(&mut grid, 24, 5);
+        let mut cells: HashMap<(i64, i64), u8> = grid.iter().map(|c| ((c.0, c.1), grid.get(c).unwrap().0)).collect();
+        for _ in 0..6 {
+            cells = brute_force(&rule, &cells);
+            grid.step();
+            let got: HashMap<(i64, i64), u8> = grid.iter().map(|c| ((c.0, c.1), grid.get(c).unwrap().0)).collect();
+            assert!(got == cells, "{} at generation {}", rule, grid.generation());
+        }
+        assert!(!cells.is_empty(), "{}", rule);
+    }
+}
@@ -0,0 +1,184 @@
+use life::coord::Coord;
+use life::grid::Grid;
+use life::map::Map;
+use life::pattern::{ErrorKind, ParseError, Pattern, read_cells, read_rle, write_cells, write_rle};
+use life::state::State;
+
+mod common;
+use common::shape;
+
+#[test]
+fn cells_round_trip() {
+    let mut grid: Map<i64> = Map::new_from_str_array(vec![
+        "                        X           ",
+        "                      X X           ",
+        "            XX      XX            XX",
+        "           X   X    XX            XX",
+        "XX        X     X   XX              ",
+        "XX        X   X XX    X X           ",
+        "          X     X       X           ",
+        "           X   X                    ",
+        "            XX                      ",
+    ]);
+    // Moved away from the origin, with an empty row and column inside the bounding box
+    grid.step_n(3);
+    grid.set(Coord(-40, 30), State::ALIVE);
+
+    let file = std::env::temp_dir().join(format!("life-round-trip-{}.cells", std::process::id()));
+    let info = Pattern {
+        name: Some("Gosper glider gun".to_string()),
+        author: Some("Bill Gosper".to_string()),
+        comments: vec!["Round trip test".to_string()],
+        url: Some("https://conwaylife.com/wiki/Gosper_glider_gun".to_string()),
+        ..Pattern::default()
+    };
+    write_cells(&file, &grid, &info).unwrap();
+    let text = std::fs::read_to_string(&file).unwrap();
+    let pattern = read_cells(&file).unwrap();
+    std::fs::remove_file(&file).unwrap();
+
+    assert!(text.starts_with("!Name: Gosper glider gun\n!Author: Bill Gosper\n!Round trip test\n"));
+    assert!(text.lines().skip(4).all(|l| l.chars().all(|c| c == '.' || c == 'O')));
+    assert_eq!(pattern.name, info.name);
+    assert_eq!(pattern.author, info.author);
+    assert_eq!(pattern.comments, info.comments);
+    assert_eq!(pattern.url, info.url);
+    let mut read: Vec<(i64, i64)> = pattern.cells.iter().map(|(c, _)| (c.0, c.1)).collect();
+    read.sort();
+    assert_eq!(read, shape(&grid));
+}
+
+// Cells with their states relative to the upper left corner of the bounding box, sorted
+fn states(grid: &dyn Grid<i64>) -> Vec<(i64, i64, u8)> {
+    let (ul, _) = grid.dims();
+    let mut res: Vec<(i64, i64, u8)> = grid.iter().map(|c| (c.0 - ul.0, c.1 - ul.1, grid.get(c).unwrap().0)).collect();
+    res.sort();
+    res
+}
+
+fn rle_round_trip(grid: &Map<i64>, info: &Pattern) -> (String, Pattern) {
+    let file = std::env::temp_dir().join(format!("life-round-trip-{}-{}.rle", std::process::id(), grid.population()));
+    write_rle(&file, grid, info).unwrap();
+    let text = std::fs::read_to_string(&file).unwrap();
+    let pattern = read_rle(&file).unwrap();
+    std::fs::remove_file(&file).unwrap();
+    assert!(text.lines().all(|l| l.len() <= 70), "{}", text);
+    let mut read: Vec<(i64, i64, u8)> = pattern.cells.iter().map(|(c, s)| (c.0, c.1, s.0)).collect();
+    read.sort();
+    assert_eq!(read, states(grid));
+    assert_eq!(pattern.rule.as_ref().map(|r| r.to_string()), Some(grid.rule().to_string()));
+    (text, pattern)
+}
+
+#[test]
+fn rle_round_trip_two_states() {
+    // Long rows of short runs are wrapped
+    let mut grid: Map<i64> = Map::new();
+    for x in 0..300 {
+        if x % 3 != 1 {
+            grid.set(Coord(x - 100, -7), State::ALIVE);
+        }
+        if x % 7 == 0 {
+            grid.set(Coord(x - 100, -4), State::ALIVE);
+        }
+    }
+    let info = Pattern {
+        name: Some("Rows".to_string()),
+        author: Some("Nobody".to_string()),
+        comments: vec!["First".to_string(), "Second".to_string()],
+        url: Some("https://conwaylife.com/wiki/Run_Length_Encoded".to_string()),
+        ..Pattern::default()
+    };
+    let (text, pattern) = rle_round_trip(&grid, &info);
+    assert!(text.starts_with("#N Rows\n#O Nobody\n#C First\n#C Second\n#C https://conwaylife.com/wiki/Run_Length_Encoded\n"));
+    assert!(text.contains("x = 300, y = 4, rule = B3/S23\n"));
+    assert!(text.contains("2o"));
+    assert!(!text.contains('A'));
+    assert_eq!(pattern.name, info.name);
+    assert_eq!(pattern.author, info.author);
+    assert_eq!(pattern.comments, info.comments);
+    assert_eq!(pattern.url, info.url);
+}
+
+#[test]
+fn rle_round_trip_multi_state() {
+    let mut grid: Map<i64> = Map::new();
+    grid.set_rule("B2/S34/C255:T200,100".parse().unwrap());
+    for (x, s) in [(0, 1), (1, 1), (2, 1), (3, 2), (5, 24), (6, 25), (7, 48), (8, 49), (9, 200), (10, 254), (11, 254)] {
+        grid.set(Coord(x, 0), State(s));
+    }
+    for x in 0..90 {
+        grid.set(Coord(x - 45, 3), State(1 + (x * 37 % 254) as u8));
+    }
+    let (text, _) = rle_round_trip(&grid, &Pattern::default());
+    assert!(text.contains("rule = B2/S34/C255:T200,100\n"));
+    assert!(text.contains("3AB.XpApXqAwH2yN"), "{}", text);
+}
+
+fn parse_rle(name: &str, text: &str) -> Result<Pattern, ParseError> {
+    let file = std::env::temp_dir().join(format!("life-parse-{}-{}.rle", std::process::id(), name));
+    std::fs::write(&file, text).unwrap();
+    let res = read_rle(&file);
+    std::fs::remove_file(&file).unwrap();
+    res
+}
+
+// Kind of the error, with its line and column
+fn error(name: &str, text: &str) -> (ErrorKind, usize, usize) {
+    let e = parse_rle(name, text).err().unwrap();
+    (e.kind, e.line, e.column)
+}
+
+#[test]
+fn rle_errors() {
+    assert!(matches!(read_rle("/nonexistent/pattern.rle").err().unwrap(), ParseError { kind: ErrorKind::Io(_), line: 0, column: 0 }));
+    assert!(matches!(error("zero", "x = 2, y = 1\n0o!\n"), (ErrorKind::BadCount(n), 2, 2) if n == "0"));
+    assert!(matches!(error("dangling", "x = 2, y = 1\no3!\n"), (ErrorKind::BadCount(n), 2, 3) if n == "3"));
+    assert!(matches!(error("char", "x = 2, y = 1\n\no?!\n"), (ErrorKind::UnexpectedChar('?'), 3, 2)));
+    assert!(matches!(error("prefix", "x = 2, y = 1\npo!\n"), (ErrorKind::UnexpectedChar('o'), 2, 2)));
+    assert!(matches!(error("end", "x = 2, y = 1\n2o$\nbo\n"), (ErrorKind::MissingEnd, 3, 3)));
+    assert!(matches!(error("state", "x = 1, y = 1\nyP!\n"), (ErrorKind::UnsupportedState(s), 2, 2) if s == "yP"));
+}
+
+#[test]
+fn rle_headers() {
+    for (name, text) in [
+        ("size", "x = a, y = 1\no!\n"),
+        ("key", "x = 1, z = 1\no!\n"),
+        ("entry", "x = 1, y\no!\n"),
+        ("rule", "x = 1, y = 1, rule = B9/S\no!\n"),
+        ("topology", "x = 1, y = 1, rule = B3/S23:é\no!\n"),
+    ] {
+        assert!(matches!(error(name, text), (ErrorKind::InvalidHeader(_), 1, 1)), "{}", name);
+    }
+    // The body must fit into the declared size
+    assert!(matches!(error("larger", "x = 1, y = 1\n2o!\n"), (ErrorKind::InvalidHeader(_), 2, 3)));
+    assert!(matches!(error("taller", "x = 1, y = 1\no$o!\n"), (ErrorKind::InvalidHeader(_), 2, 4)));
+    let pattern = parse_rle("smaller", "x = 5, y = 3, rule = B36/S23\no!\n").unwrap();
+    assert_eq!((pattern.width, pattern.height), (Some(5), Some(3)));
+    assert_eq!(pattern.rule.unwrap().to_string(), "B36/S23");
+}
+
+#[test]
+fn rle_positions() {
+    let pattern = parse_rle("p", "#P 3 -4\nx = 1, y = 1\no!\n").unwrap();
+    assert!(pattern.offset.is_some_and(|c| c.0 == 3 && c.1 == -4));
+    let pattern = parse_rle("r", "#R -10 20\nx = 1, y = 1\no!\n").unwrap();
+    assert!(pattern.offset.is_some_and(|c| c.0 == -10 && c.1 == 20));
+    assert!(matches!(error("missing", "#P 3\nx = 1, y = 1\no!\n"), (ErrorKind::InvalidHeader(_), 1, 1)));
+    assert!(matches!(error("far", "x = 1, y = 1\n#P 9000000000000000 0\no!\n"), (ErrorKind::InvalidHeader(_), 2, 1)));
+    assert!(matches!(error("min", "#R 0 -9223372036854775808\nx = 1, y = 1\no!\n"), (ErrorKind::InvalidHeader(_), 1, 1)));
+}
+
+#[test]
+fn rle_states() {
+    let pattern = parse_rle("states", "x = 6, y = 1, rule = B2/S/C255\nA.pAyO2X!\n").unwrap();
+    let states: Vec<(i64, u8)> = pattern.cells.iter().map(|(c, s)| (c.0, s.0)).collect();
+    assert_eq!(states, vec![(0, 1), (2, 25), (3, 255), (4, 24), (5, 24)]);
+
+    // Odd LifeHistory states are alive, the rule is Life
+    let pattern = parse_rle("history", "x = 6, y = 1, rule = LifeHistory:T10,10\nABCDEF!\n").unwrap();
+    let cells: Vec<(i64, u8)> = pattern.cells.iter().map(|(c, s)| (c.0, s.0)).collect();
+    assert_eq!(cells, vec![(0, 1), (2, 1), (4, 1)]);
+    assert_eq!(pattern.rule.unwrap().to_string(), "B3/S23:T10,10");
+}
@@ -0,0 +1,59 @@
+use life::coord::Coord;
+use life::grid::Grid;
+use life::hashlife::HashLife;
+use life::map::Map;
+use life::period::PeriodDetector;
+use life::state::State;
+
+fn detect(grid: &mut dyn Grid<i64>, rows: &[&str], generations: u64) -> Option<String> {
+    for (y, row) in rows.iter().enumerate() {
+        for (x, c) in row.chars().enumerate() {
+            if c != ' ' {
+                grid.set(Coord(x as i64, y as i64), State::ALIVE);
+            }
+        }
+    }
+    let mut detector = PeriodDetector::new();
+    for _ in 0..50 {
+        grid.advance(generations);
+        if let Some(period) = detector.check(grid) {
+            return Some(period.to_string());
+        }
+    }
+    None
+}
+
+#[test]
+fn life_patterns() {
+    assert_eq!(detect(&mut Map::new(), &["OO", "OO"], 1).as_deref(), Some("still life"));
+    assert_eq!(detect(&mut Map::new(), &["OOO"], 1).as_deref(), Some("oscillator period 2"));
+    assert_eq!(detect(&mut Map::new(), &[" O", "  O", "OOO"], 1).as_deref(), Some("spaceship period 4, displacement (1,1)"));
+    // The R-pentomino does not settle within 50 generations
+    assert_eq!(detect(&mut Map::new(), &[" OO", "OO", " O"], 1), None);
+}
+
+#[test]
+fn skipped_generations() {
+    assert_eq!(detect(&mut Map::new(), &["OO", "OO"], 5).as_deref(), Some("period divides 5"));
+    assert_eq!(detect(&mut Map::new(), &["OOO"], 4).as_deref(), Some("period divides 4"));
+
+    let mut hashlife = HashLife::new();
+    hashlife.set_step_exp(3);
+    assert_eq!(detect(&mut hashlife, &["OO", "OO"], 8).as_deref(), Some("period divides 8"));
+    let mut hashlife = HashLife::new();
+    hashlife.set_step_exp(3);
+    assert_eq!(detect(&mut hashlife, &[" O", "  O", "OOO"], 8).as_deref(), Some("spaceship period divides 8, displacement (2,2) over 8"));
+}
+
+#[test]
+fn block_rules_compare_same_parity() {
+    let mut bbm: Map<i64> = Map::new();
+    bbm.set_rule("BBM".parse().unwrap());
+    assert_eq!(detect(&mut bbm, &["O"], 1).as_deref(), Some("spaceship period 2, displacement (2,2)"));
+
+    // Two cells of Critters come back after 8 generations, while the same shape shows up
+    // shifted by one cell in between
+    let mut critters: Map<i64> = Map::new();
+    critters.set_rule("Critters".parse().unwrap());
+    assert_eq!(detect(&mut critters, &["OO"], 1).as_deref(), Some("oscillator period 8"));
+}
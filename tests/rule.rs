@@ -0,0 +1,84 @@
+use life::coord::Coord;
+use life::grid::Grid;
+use life::map::Map;
+use life::rule::Rule;
+use life::state::State;
+
+mod common;
+use common::cells;
+
+// Hensel letters of each number of alive neighbors, as in Golly
+const LETTERS: [&str; 9] = ["", "ce", "ceaikn", "ceaiknjqry", "ceaiknjqrytwz", "ceaiknjqry", "ceaikn", "ce", ""];
+
+fn survives(rule: &Rule, config: u8) -> bool {
+    rule.next_state(Some(State::ALIVE), config) == Some(State::ALIVE)
+}
+
+#[test]
+fn hensel_letters_partition_configurations() {
+    for (n, letters) in LETTERS.iter().enumerate() {
+        let rules: Vec<Rule> = letters.chars().map(|l| format!("B/S{}{}", n, l).parse().unwrap()).collect();
+        let all: Rule = format!("B/S{}", n).parse().unwrap();
+        for config in 0..=255u8 {
+            let classes = rules.iter().filter(|r| survives(r, config)).count();
+            if config.count_ones() as usize == n {
+                assert!(survives(&all, config));
+                assert!(letters.is_empty() || classes == 1, "{} neighbors, configuration {}", n, config);
+            } else {
+                assert!(!survives(&all, config));
+                assert_eq!(classes, 0);
+            }
+        }
+    }
+}
+
+#[test]
+fn display_round_trips() {
+    for text in [
+        "B3/S23",
+        "B36/S23",
+        "B2/S/C3",
+        "B3/S23V",
+        "B2/S34H",
+        "B2-a/S12",
+        "B3aeijk/S2-a3",
+        "B2c3ae4ai/S2c3-a4t5",
+        "R5,C0,M1,S34..58,B34..45,NM",
+        "R3,C3,M0,S5..12,B7..10,NN",
+        "MS,D0;8;4;3;2;5;9;7;1;6;10;11;12;13;14;15",
+        "B3/S23:T64,64",
+        "B3/S23:K30*,20",
+    ] {
+        let rule: Rule = text.parse().unwrap();
+        let written = rule.to_string();
+        let again: Rule = written.parse().unwrap();
+        assert!(again == rule, "{} written as {}", text, written);
+        assert_eq!(again.to_string(), written);
+    }
+}
+
+#[test]
+fn isotropic_rules_evolve() {
+    // Two cells with a gap: the cell between them sees them as opposite edges (2i), the
+    // cells above and below as corners on the same side (2c)
+    for (rule, expected) in [("B2i/S", vec![(1, 0)]), ("B2c/S", vec![(1, -1), (1, 1)]), ("B2/S", vec![(1, -1), (1, 0), (1, 1)])] {
+        let mut grid: Map<i64> = Map::new();
+        grid.set_rule(rule.parse().unwrap());
+        grid.set(Coord(0, 0), State::ALIVE);
+        grid.set(Coord(2, 0), State::ALIVE);
+        grid.step();
+        assert_eq!(cells(&grid), expected, "{}", rule);
+    }
+
+    // Life spelled out with all the letters moves the glider as usual
+    let mut grid: Map<i64> = Map::new_from_str_array(vec![
+        " X ",
+        "  X",
+        "XXX",
+    ]);
+    let glider = cells(&grid);
+    grid.set_rule("B3ceaiknjqry/S2ceaikn3ceaiknjqry".parse().unwrap());
+    assert!(grid.rule().is_totalistic());
+    grid.step_n(4);
+    assert_eq!(cells(&grid), glider.iter().map(|(x, y)| (x + 1, y + 1)).collect::<Vec<_>>());
+}
@@ -0,0 +1,108 @@
+use life::coord::Coord;
+use life::map::Map;
+use life::rule::Rule;
+use life::state::State;
+use life::table::{parse_rule_file, read_rule_file};
+
+fn states(grid: &Map<i64>) -> Vec<(i64, i64, u8)> {
+    let mut res: Vec<(i64, i64, u8)> = grid.iter().map(|c| (c.0, c.1, grid.get(c).unwrap().0)).collect();
+    res.sort();
+    res
+}
+
+fn grid(rule: &str, cells: &[(i64, i64, u8)]) -> Map<i64> {
+    let mut grid: Map<i64> = Map::new();
+    grid.set_rule(Rule::with_table(parse_rule_file(rule).unwrap()));
+    for (x, y, s) in cells {
+        grid.set(Coord(*x, *y), State(*s));
+    }
+    grid
+}
+
+#[test]
+fn wireworld_electron() {
+    let mut grid: Map<i64> = Map::new();
+    grid.set_rule(read_rule_file("rules/Wireworld.rule").unwrap());
+    // Tail and head at the left end of a wire
+    for x in 0..10 {
+        grid.set(Coord(x, 0), State(3));
+    }
+    grid.set(Coord(0, 0), State(2));
+    grid.set(Coord(1, 0), State(1));
+    for g in 1..=8 {
+        grid.step();
+        let expected: Vec<(i64, i64, u8)> = (0..10).map(|x| (x, 0, match x - g {
+            1 => 1,
+            0 => 2,
+            _ => 3,
+        })).collect();
+        assert_eq!(states(&grid), expected);
+    }
+}
+
+#[test]
+fn von_neumann_compact_rotate4() {
+    let rule = "@RULE Spread\n@TABLE\nn_states:3\nneighborhood:vonNeumann\nsymmetries:rotate4\n\n# A cell next to a 1 on one side becomes 2\n010002\n";
+    let mut grid = grid(rule, &[(0, 0, 1)]);
+    grid.step();
+    assert_eq!(states(&grid), vec![(-1, 0, 2), (0, -1, 2), (0, 0, 1), (0, 1, 2), (1, 0, 2)]);
+    // Cells seeing a 2 or two cells do not match
+    grid.step();
+    assert_eq!(states(&grid), vec![(-1, 0, 2), (0, -1, 2), (0, 0, 1), (0, 1, 2), (1, 0, 2)]);
+}
+
+#[test]
+fn bound_variables() {
+    // Cells between two equal states above and below become 1
+    let rule = "@RULE Bound\n@TABLE\nn_states:3\nneighborhood:Moore\nsymmetries:none\nvar a={1,2}\n0,a,0,0,0,a,0,0,0,1\n";
+    let mut same = grid(rule, &[(0, -1, 2), (0, 1, 2)]);
+    same.step();
+    assert_eq!(states(&same), vec![(0, -1, 2), (0, 0, 1), (0, 1, 2)]);
+    let mut different = grid(rule, &[(0, -1, 1), (0, 1, 2)]);
+    different.step();
+    assert_eq!(states(&different), vec![(0, -1, 1), (0, 1, 2)]);
+}
+
+#[test]
+fn output_variables() {
+    // Cells below a state copy it
+    let rule = "@RULE Copy\n@TABLE\nn_states:3\nneighborhood:Moore\nsymmetries:none\nvar a={1,2}\n0,a,0,0,0,0,0,0,0,a\n";
+    let mut grid = grid(rule, &[(0, 0, 1), (5, 0, 2)]);
+    grid.step();
+    assert_eq!(states(&grid), vec![(0, 0, 1), (0, 1, 1), (5, 0, 2), (5, 1, 2)]);
+
+    let unbound = "@RULE Unbound\n@TABLE\nn_states:3\nvar a={1,2}\nvar b={1,2}\n0,a,0,0,0,0,0,0,0,b\n";
+    assert!(parse_rule_file(unbound).is_err());
+}
+
+#[test]
+fn invalid_rules() {
+    let birth = "@RULE Birth\n@TABLE\nn_states:2\n0,0,0,0,0,0,0,0,0,1\n";
+    assert_eq!(parse_rule_file(birth).err().unwrap(), "Rule Birth brings empty cells to life");
+    assert!(parse_rule_file("@RULE NoTable\n").is_err());
+    assert!(parse_rule_file("@TABLE\nn_states:2\n").is_err());
+    assert!(parse_rule_file("@RULE Short\n@TABLE\nn_states:2\n0,1,1\n").is_err());
+}
+
+#[test]
+fn colors() {
+    let rule = "@RULE Colors\n@TABLE\nn_states:4\n@COLORS\n255 255 255 0 0 255\n0 0 0 0 0 0 0 0 0 0 0 0\n3 0 255 0\n";
+    let table = parse_rule_file(rule).unwrap();
+    // Explicit colors win over the gradient, unknown lines are skipped
+    assert_eq!(table.color(1), Some((255, 255, 255)));
+    assert_eq!(table.color(2), Some((128, 128, 255)));
+    assert_eq!(table.color(3), Some((0, 255, 0)));
+}
+
+#[test]
+fn rule_next_to_pattern() {
+    let dir = std::env::temp_dir().join(format!("life-rules-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    std::fs::write(dir.join("Spread.rule"), "@RULE Spread\n@TABLE\nn_states:3\nneighborhood:vonNeumann\nsymmetries:rotate4\n010002\n").unwrap();
+    std::fs::write(dir.join("spread.rle"), "x = 1, y = 1, rule = spread\nA!\n").unwrap();
+    let pattern = life::pattern::read_rle(dir.join("spread.rle"));
+    std::fs::remove_dir_all(&dir).unwrap();
+    assert_eq!(pattern.unwrap().rule.unwrap().to_string(), "Spread");
+    // Not found from elsewhere
+    assert!("Spread".parse::<Rule>().is_err());
+}
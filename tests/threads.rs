@@ -0,0 +1,27 @@
+use life::grid::Grid;
+use life::map::Map;
+use life::tiles::TileGrid;
+
+mod common;
+use common::{cells, soup};
+
+fn run(grid: &mut dyn Grid<i64>, threads: usize) -> Vec<(i64, i64)> {
+    grid.set_threads(threads);
+    soup(grid, 64, 42);
+    grid.step_n(60);
+    cells(grid)
+}
+
+#[test]
+fn map_threads_match_sequential() {
+    let sequential = run(&mut Map::new(), 1);
+    assert!(!sequential.is_empty());
+    assert_eq!(run(&mut Map::new(), 4), sequential);
+}
+
+#[test]
+fn tiles_threads_match_sequential() {
+    let sequential = run(&mut TileGrid::new(), 1);
+    assert!(!sequential.is_empty());
+    assert_eq!(run(&mut TileGrid::new(), 4), sequential);
+}
@@ -0,0 +1,109 @@
+use life::coord::Coord;
+use life::grid::Grid;
+use life::map::Map;
+use life::rule::Rule;
+use life::topology::Topology;
+
+fn wrap(topology: &str, x: i32, y: i32) -> Option<(i32, i32)> {
+    let topology: Topology = topology.parse().unwrap();
+    topology.wrap(Coord(x, y)).map(|c| (c.0, c.1))
+}
+
+// All the universes are 4x4, from (-2, -2) to (1, 1)
+#[test]
+fn neighbors_across_edges() {
+    assert_eq!(wrap("P4,4", 1, 1), Some((1, 1)));
+    assert_eq!(wrap("P4,4", 2, 0), None);
+    assert_eq!(wrap("P4,4", 0, -3), None);
+
+    assert_eq!(wrap("T4,4", 2, 0), Some((-2, 0)));
+    assert_eq!(wrap("T4,4", 0, -3), Some((0, 1)));
+    assert_eq!(wrap("T4,4", -3, -3), Some((1, 1)));
+
+    // Crossing the top or bottom edge mirrors the column, the left and right edges are plain
+    assert_eq!(wrap("K4*,4", 2, 0), Some((-2, 0)));
+    assert_eq!(wrap("K4*,4", -2, -3), Some((1, 1)));
+    assert_eq!(wrap("K4*,4", 0, 2), Some((-1, -2)));
+    // And the other way around
+    assert_eq!(wrap("K4,4*", -2, -3), Some((-2, 1)));
+    assert_eq!(wrap("K4,4*", 2, -2), Some((-2, 1)));
+
+    // Both pairs of edges are twisted
+    assert_eq!(wrap("C4,4", 2, -1), Some((-2, 0)));
+    assert_eq!(wrap("C4,4", -1, 2), Some((0, -2)));
+
+    // The top edge is glued to the left one and the bottom edge to the right one
+    assert_eq!(wrap("S4", -1, -3), Some((-2, -1)));
+    assert_eq!(wrap("S4", -3, 0), Some((0, -2)));
+    assert_eq!(wrap("S4", 2, -1), Some((-1, 1)));
+    assert_eq!(wrap("S4", 0, 2), Some((1, 0)));
+    assert_eq!(wrap("S4", -3, -3), None);
+}
+
+// A zero size leaves the dimension unbounded
+#[test]
+fn infinite_strips() {
+    assert_eq!(wrap("T0,4", 100, 2), Some((100, -2)));
+    assert_eq!(wrap("T0,4", -100, -3), Some((-100, 1)));
+    assert_eq!(wrap("T4,0", 2, -100), Some((-2, -100)));
+    assert_eq!(wrap("P0,4", 100, 1), Some((100, 1)));
+    assert_eq!(wrap("P0,4", 100, 2), None);
+    assert_eq!(wrap("P4,0", -3, 0), None);
+    assert_eq!(wrap("T0,0", 100, 100), Some((100, 100)));
+
+    for s in ["T0,64", "P64,0"] {
+        assert_eq!(s.parse::<Topology>().unwrap().to_string(), s);
+    }
+    let topology: Topology = "T0,4".parse().unwrap();
+    assert!(topology.bounds::<i32>().is_none());
+    assert!(topology.contains(Coord(1000, 1)));
+    assert!(!topology.contains(Coord(0, 2)));
+}
+
+#[test]
+fn invalid_topologies() {
+    assert!("B3/S23:é".parse::<Rule>().is_err());
+    assert!("B3/S23:X4,4".parse::<Rule>().is_err());
+    assert!("B3/S23:S4,5".parse::<Rule>().is_err());
+    assert!("B3/S23:T-4,4".parse::<Rule>().is_err());
+    assert!("B3/S23:K0,4*".parse::<Rule>().is_err());
+    assert!("B3/S23:C4,0".parse::<Rule>().is_err());
+    assert!("B3/S23:S0".parse::<Rule>().is_err());
+}
+
+#[test]
+fn glider_crosses_torus_edges() {
+    let mut grid: Map<i64> = Map::new_from_str_array(vec![
+        " X ",
+        "  X",
+        "XXX",
+    ]);
+    grid.set_rule("B3/S23:T8,8".parse().unwrap());
+    let mut start: Vec<(i64, i64)> = grid.iter().map(|c| (c.0, c.1)).collect();
+    start.sort();
+    // The glider moves by one cell diagonally every four generations
+    for _ in 0..8 {
+        grid.step_n(4);
+        assert_eq!(grid.population(), 5);
+    }
+    let mut end: Vec<(i64, i64)> = grid.iter().map(|c| (c.0, c.1)).collect();
+    end.sort();
+    assert_eq!(end, start);
+}
+
+#[test]
+fn glider_crosses_strip_edge() {
+    let mut grid: Map<i64> = Map::new_from_str_array(vec![
+        " X ",
+        "  X",
+        "XXX",
+    ]);
+    grid.set_rule("B3/S23:T0,8".parse().unwrap());
+    let mut start: Vec<(i64, i64)> = grid.iter().map(|c| (c.0 + 8, c.1)).collect();
+    start.sort();
+    // After crossing the bottom edge the glider is back in the same rows, 8 columns to the right
+    grid.step_n(32);
+    let mut end: Vec<(i64, i64)> = grid.iter().map(|c| (c.0, c.1)).collect();
+    end.sort();
+    assert_eq!(end, start);
+}